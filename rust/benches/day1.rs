@@ -0,0 +1,48 @@
+//! Scaling of the day 1 radix versions against the original ones.
+//!
+//! cargo bench --bench day1
+
+use aoc24::rng::XorShift;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+#[allow(dead_code)]
+#[path = "../src/bin/day1.rs"]
+mod day1;
+
+// Location lists shaped like the puzzle input, with five digit IDs
+fn generate(rows: usize) -> String {
+    let mut rng = XorShift::new(0x2545f4914f6cdd1d);
+    let mut next = || 10000 + rng.below(90000);
+    (0..rows)
+        .map(|_| format!("{}   {}\n", next(), next()))
+        .collect()
+}
+
+fn scaling(c: &mut Criterion) {
+    let mut group = c.benchmark_group("day1");
+    group.sample_size(10);
+    for rows in [10_000, 100_000, 1_000_000] {
+        let input = generate(rows);
+        group.throughput(Throughput::Elements(rows as u64));
+        group.bench_with_input(BenchmarkId::new("part_one", rows), &input, |b, input| {
+            b.iter(|| day1::part_one(input))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("part_one_radix", rows),
+            &input,
+            |b, input| b.iter(|| day1::part_one_radix(input.as_bytes())),
+        );
+        group.bench_with_input(BenchmarkId::new("part_two", rows), &input, |b, input| {
+            b.iter(|| day1::part_two(input))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("part_two_radix", rows),
+            &input,
+            |b, input| b.iter(|| day1::part_two_radix(input.as_bytes())),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, scaling);
+criterion_main!(benches);
@@ -13,7 +13,7 @@ fn parse(input: &str) -> (Vec<i32>, Vec<i32>) {
         })
 }
 
-fn part_one(input: &str) -> i32 {
+pub fn part_one(input: &str) -> i32 {
     let (mut left, mut right) = parse(input);
     left.sort();
     right.sort();
@@ -24,7 +24,7 @@ fn part_one(input: &str) -> i32 {
         .sum()
 }
 
-fn part_two(input: &str) -> i32 {
+pub fn part_two(input: &str) -> i32 {
     let (left, right) = parse(input);
 
     let mut count: HashMap<i32, i32> = HashMap::default();
@@ -38,7 +38,139 @@ fn part_two(input: &str) -> i32 {
         .sum()
 }
 
-aoc24::aoc!(part_one, part_two);
+// Byte-level versions, for location lists with hundreds of millions of rows. They take the
+// memory-mapped bytes as is, and sort with a radix sort instead of a comparison sort.
+// Location IDs are unsigned, and columns can be separated by any amount of whitespace.
+
+#[derive(Debug, PartialEq, Eq)]
+enum ParseError {
+    /// A location ID that doesn't fit in a `u32`
+    Overflow {
+        line: usize,
+    },
+    UnexpectedByte {
+        line: usize,
+        byte: u8,
+    },
+    /// A line without exactly two numbers, empty lines aside
+    NumbersOnLine {
+        line: usize,
+        count: usize,
+    },
+}
+
+// Both columns in a single pass over the bytes. Every line is checked on its own, so a bad
+// line can't shift the following ones into the wrong column.
+fn parse_columns(bytes: &[u8]) -> Result<(Vec<u32>, Vec<u32>), ParseError> {
+    let mut columns = [Vec::new(), Vec::new()];
+    // Numbers on the current line so far
+    let mut count = 0;
+    let mut number: Option<u32> = None;
+    let mut line = 1;
+    let push = |columns: &mut [Vec<u32>; 2], count: &mut usize, n: u32| {
+        if let Some(column) = columns.get_mut(*count) {
+            column.push(n);
+        }
+        *count += 1;
+    };
+    let end_line = |line: usize, count: usize| match count {
+        0 | 2 => Ok(()),
+        count => Err(ParseError::NumbersOnLine { line, count }),
+    };
+
+    for &b in bytes {
+        match b {
+            b'0'..=b'9' => {
+                let n = number
+                    .unwrap_or(0)
+                    .checked_mul(10)
+                    .and_then(|n| n.checked_add((b - b'0') as u32))
+                    .ok_or(ParseError::Overflow { line })?;
+                number = Some(n);
+            }
+            b if b.is_ascii_whitespace() => {
+                if let Some(n) = number.take() {
+                    push(&mut columns, &mut count, n);
+                }
+                if b == b'\n' {
+                    end_line(line, count)?;
+                    count = 0;
+                    line += 1;
+                }
+            }
+            byte => return Err(ParseError::UnexpectedByte { line, byte }),
+        }
+    }
+    if let Some(n) = number {
+        push(&mut columns, &mut count, n);
+    }
+    end_line(line, count)?;
+
+    let [left, right] = columns;
+    Ok((left, right))
+}
+
+// LSD radix sort, one byte per pass. Passes where every value has the same digit are skipped.
+fn radix_sort(values: &mut Vec<u32>) {
+    let mut counts = [[0usize; 256]; 4];
+    for v in values.iter() {
+        for (digit, count) in counts.iter_mut().enumerate() {
+            count[(v >> (digit * 8)) as usize & 0xff] += 1;
+        }
+    }
+
+    let mut scratch = vec![0; values.len()];
+    for (digit, count) in counts.iter().enumerate() {
+        if count.contains(&values.len()) {
+            continue;
+        }
+        let mut offsets = [0usize; 256];
+        for bucket in 1..256 {
+            offsets[bucket] = offsets[bucket - 1] + count[bucket - 1];
+        }
+        for v in values.iter() {
+            let bucket = (v >> (digit * 8)) as usize & 0xff;
+            scratch[offsets[bucket]] = *v;
+            offsets[bucket] += 1;
+        }
+        std::mem::swap(values, &mut scratch);
+    }
+}
+
+pub fn part_one_radix(input: &[u8]) -> u64 {
+    let (mut left, mut right) = parse_columns(input).expect("Failed to parse location lists");
+    radix_sort(&mut left);
+    radix_sort(&mut right);
+
+    left.into_iter()
+        .zip(right)
+        .map(|(a, b)| a.abs_diff(b) as u64)
+        .sum()
+}
+
+// Both lists sorted, so the occurrences are counted by walking runs of equal IDs side by side.
+pub fn part_two_radix(input: &[u8]) -> u64 {
+    let (mut left, mut right) = parse_columns(input).expect("Failed to parse location lists");
+    radix_sort(&mut left);
+    radix_sort(&mut right);
+
+    let mut total = 0;
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() && j < right.len() {
+        let x = left[i];
+        let left_run = left[i..].iter().take_while(|v| **v == x).count();
+        while j < right.len() && right[j] < x {
+            j += 1;
+        }
+        let right_run = right[j..].iter().take_while(|v| **v == x).count();
+        total += x as u64 * left_run as u64 * right_run as u64;
+        i += left_run;
+        j += right_run;
+    }
+    total
+}
+
+aoc24::aoc!(part_one, part_two, part_one_radix, part_two_radix);
 
 #[cfg(test)]
 pub mod tests {
@@ -54,4 +186,83 @@ pub mod tests {
         assert_eq!(super::part_one(TEST_INPUT), 11);
         assert_eq!(super::part_two(TEST_INPUT), 31);
     }
+
+    #[test]
+    fn day1_radix() {
+        assert_eq!(super::part_one_radix(TEST_INPUT.as_bytes()), 11);
+        assert_eq!(super::part_two_radix(TEST_INPUT.as_bytes()), 31);
+
+        let messy = b"3\t4\n4 3\r\n  2     5\n1   3\n3   9\n3\t\t3";
+        assert_eq!(super::part_one_radix(messy), 11);
+        assert_eq!(super::part_two_radix(messy), 31);
+    }
+
+    #[test]
+    fn day1_parse_errors() {
+        use super::{parse_columns, ParseError};
+
+        assert_eq!(
+            parse_columns(b"1   4294967295\n"),
+            Ok((vec![1], vec![u32::MAX]))
+        );
+        assert_eq!(
+            parse_columns(b"1   2\n1   4294967296\n"),
+            Err(ParseError::Overflow { line: 2 })
+        );
+        assert_eq!(
+            parse_columns(b"1   99999999999999999999"),
+            Err(ParseError::Overflow { line: 1 })
+        );
+        assert_eq!(
+            parse_columns(b"1   2\n3,  4"),
+            Err(ParseError::UnexpectedByte {
+                line: 2,
+                byte: b','
+            })
+        );
+        assert_eq!(
+            parse_columns(b"1   2\n3"),
+            Err(ParseError::NumbersOnLine { line: 2, count: 1 })
+        );
+        assert_eq!(
+            parse_columns(b"1   2   3\n4   5   6\n"),
+            Err(ParseError::NumbersOnLine { line: 1, count: 3 })
+        );
+        assert_eq!(
+            parse_columns(b"1 2 3\n4\n"),
+            Err(ParseError::NumbersOnLine { line: 1, count: 3 })
+        );
+        assert_eq!(
+            parse_columns(b"1   2\n\n3   4\n"),
+            Ok((vec![1, 3], vec![2, 4]))
+        );
+    }
+
+    #[test]
+    fn day1_radix_sort() {
+        let mut values = vec![70000, 3, 1 << 31, 256, 255, 3, 0, 65536];
+        let mut expected = values.clone();
+        expected.sort();
+        super::radix_sort(&mut values);
+        assert_eq!(values, expected);
+    }
+
+    #[test]
+    fn day1_radix_matches_sort() {
+        use aoc24::rng::XorShift;
+
+        let mut rng = XorShift::new(0x2545f4914f6cdd1d);
+        let mut next = || 10000 + rng.below(90000);
+        let input: String = (0..2000)
+            .map(|_| format!("{}   {}\n", next(), next()))
+            .collect();
+        assert_eq!(
+            super::part_one(&input) as u64,
+            super::part_one_radix(input.as_bytes())
+        );
+        assert_eq!(
+            super::part_two(&input) as u64,
+            super::part_two_radix(input.as_bytes())
+        );
+    }
 }
@@ -1,5 +1,7 @@
-use aoc24::aoc;
-use fxhash::FxHashMap;
+use aoc24::{
+    aoc,
+    memo::{Memo, MemoStats},
+};
 
 fn parse(input: &str) -> Vec<i64> {
     input
@@ -21,16 +23,12 @@ fn split_tuple(i: i64) -> (i64, i64) {
     (a, b)
 }
 
-fn number_of_stones_next(memo: &mut FxHashMap<(i64, i64), usize>, v: i64, max: i64) -> usize {
+fn number_of_stones_next(memo: &mut Memo<(i64, i64), usize>, v: i64, max: i64) -> usize {
     if max == 0 {
         return 1;
     }
 
-    if let Some(nb) = memo.get(&(v, max)) {
-        return *nb;
-    }
-
-    let result = match v {
+    memo.get_or_compute((v, max), |memo| match v {
         0 => number_of_stones_next(memo, 1, max - 1),
         x if even_number_of_digits(x) => {
             let (a, b) = split_tuple(x);
@@ -39,30 +37,38 @@ fn number_of_stones_next(memo: &mut FxHashMap<(i64, i64), usize>, v: i64, max: i
             a + b
         }
         x => number_of_stones_next(memo, x * 2024, max - 1),
-    };
-    memo.insert((v, max), result);
-    result
+    })
 }
 
-fn part_one(input: &str) -> usize {
+// Number of stones after blinking `times`, and how well the memo did
+fn blink(input: &str, times: i64) -> (usize, MemoStats) {
     let state = parse(input);
-    let mut memo = FxHashMap::default();
-    state
+    let mut memo = Memo::new();
+    let stones = state
         .into_iter()
-        .map(|x| number_of_stones_next(&mut memo, x, 25))
-        .sum()
+        .map(|x| number_of_stones_next(&mut memo, x, times))
+        .sum();
+    (stones, memo.stats())
+}
+
+fn part_one(input: &str) -> usize {
+    blink(input, 25).0
 }
 
 fn part_two(input: &str) -> usize {
-    let state = parse(input);
-    let mut memo = FxHashMap::default();
-    state
-        .into_iter()
-        .map(|x| number_of_stones_next(&mut memo, x, 75))
-        .sum()
+    blink(input, 75).0
+}
+
+fn memo_stats(input: &str) -> String {
+    [25, 75]
+        .map(|times| {
+            let (stones, stats) = blink(input, times);
+            format!("{times} blinks: {stones} stones, {stats}")
+        })
+        .join("\n")
 }
 
-aoc!(part_one, part_two);
+aoc!(part_one, part_two; memo_stats);
 
 #[cfg(test)]
 pub mod tests {
@@ -81,4 +87,13 @@ pub mod tests {
     fn day11() {
         assert_eq!(part_one(INPUT), 55312);
     }
+
+    #[test]
+    fn test_memo_stats() {
+        let (stones, stats) = blink(INPUT, 25);
+        assert_eq!(stones, 55312);
+        assert!(stats.hits > 0);
+        assert_eq!(stats.evictions, 0);
+        assert!(memo_stats(INPUT).starts_with("25 blinks: 55312 stones, "));
+    }
 }
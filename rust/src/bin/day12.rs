@@ -1,6 +1,6 @@
-use fxhash::{FxBuildHasher, FxHashMap, FxHashSet};
+use fxhash::{FxHashMap, FxHashSet};
 
-use aoc24::aoc;
+use aoc24::{aoc, bitset::VisitedSet, geometry::BoundingBox};
 
 fn parse(input: &str) -> FxHashMap<(i32, i32), char> {
     input
@@ -93,30 +93,41 @@ fn compute_perimeter_discounted(region: &FxHashSet<(i32, i32)>) -> usize {
         }
     }
 
-    let mut close: FxHashSet<((i32, i32), Direction)> =
-        FxHashSet::with_capacity_and_hasher(perimeter.len(), FxBuildHasher::default());
+    let Some(mut bounds) = perimeter
+        .iter()
+        .next()
+        .map(|(pos, _)| BoundingBox::new(*pos))
+    else {
+        return 0;
+    };
+    for (pos, _) in &perimeter {
+        bounds.include(*pos);
+    }
+
+    let mut close = VisitedSet::new(bounds.volume(), DIRECTIONS.len());
     let mut sides = 0;
     for (pos, dir) in &perimeter {
-        sides += populate_side_in_closed_list(&mut close, &perimeter, pos.0, pos.1, *dir);
+        sides += populate_side_in_closed_list(&mut close, &bounds, &perimeter, pos.0, pos.1, *dir);
     }
     sides
 }
 
 fn populate_side_in_closed_list(
-    close: &mut FxHashSet<((i32, i32), Direction)>,
+    close: &mut VisitedSet,
+    bounds: &BoundingBox<2>,
     perim: &FxHashSet<((i32, i32), Direction)>,
     x: i32,
     y: i32,
     dir: Direction,
 ) -> usize {
-    if close.contains(&((x, y), dir)) {
+    let cell = bounds.index((x, y)).expect("perimeter within bounds");
+    if !close.insert(cell, dir as usize) {
         return 0;
     }
-    close.insert(((x, y), dir));
     for (nx, ny) in NEIGHBORS {
         let n = (x + nx, y + ny);
         if perim.contains(&(n, dir)) {
-            populate_side_in_closed_list(close, perim, n.0, n.1, dir);
+            populate_side_in_closed_list(close, bounds, perim, n.0, n.1, dir);
         }
     }
     1
@@ -1,6 +1,10 @@
 use std::cmp::Ordering;
 
-use aoc24::aoc;
+use aoc24::{
+    aoc,
+    viz::{self, Color, Frame},
+};
+use fxhash::FxHashMap;
 use nom::{bytes::complete::tag, IResult};
 
 #[derive(Debug, PartialEq, Clone)]
@@ -48,6 +52,20 @@ fn parse(input: &str) -> Vec<Robot> {
         .collect()
 }
 
+// Number of robots on each tile, as in the puzzle drawings
+fn render(robots: &[Robot], grid_width: i32, grid_height: i32) -> Frame {
+    let mut counts: FxHashMap<(i32, i32), u32> = FxHashMap::default();
+    for robot in robots {
+        *counts.entry(robot.pos).or_default() += 1;
+    }
+    let mut frame = Frame::new(grid_width as usize, grid_height as usize);
+    for (pos, count) in counts {
+        let glyph = char::from_digit(count, 10).unwrap_or('+');
+        frame.entity(pos, glyph, Color::GREEN);
+    }
+    frame
+}
+
 fn part_one(input: &str) -> usize {
     do_part_one(input, 101, 103)
 }
@@ -55,6 +73,18 @@ fn part_one(input: &str) -> usize {
 fn do_part_one(input: &str, grid_width: i32, grid_height: i32) -> usize {
     let mut robots = parse(input);
 
+    if viz::enabled() {
+        // One frame per second, the answer only needs the last one
+        let mut moving = robots.clone();
+        viz::emit(|| render(&moving, grid_width, grid_height));
+        for _ in 0..100 {
+            for robot in &mut moving {
+                robot.simulate(grid_width, grid_height, 1);
+            }
+            viz::emit(|| render(&moving, grid_width, grid_height));
+        }
+    }
+
     let mut quads = vec![0, 0, 0, 0];
     for robot in &mut robots {
         robot.simulate(grid_width, grid_height, 100);
@@ -100,6 +130,25 @@ p=9,5 v=-3,-3";
         assert_eq!(do_part_one(INPUT, 11, 7), 12);
     }
 
+    #[test]
+    fn day14_render() {
+        let mut robots = parse(INPUT);
+        for robot in &mut robots {
+            robot.simulate(11, 7, 100);
+        }
+        assert_eq!(
+            render(&robots, 11, 7).to_text(),
+            "......2..1.
+...........
+1..........
+.11........
+.....1.....
+...12......
+.1....1....
+"
+        );
+    }
+
     #[test]
     fn day14_simulate() {
         let mut robot = Robot {
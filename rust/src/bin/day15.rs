@@ -1,6 +1,9 @@
 use std::collections::{HashMap, HashSet};
 
-use aoc24::aoc;
+use aoc24::{
+    aoc,
+    viz::{self, Color, Frame},
+};
 use maplit::hashset;
 
 #[derive(Debug, Copy, Clone)]
@@ -181,6 +184,23 @@ fn do_move(
     }
 }
 
+fn render(grid: &HashMap<(i32, i32), Tile>, pos: (i32, i32)) -> Frame {
+    let width = grid.keys().map(|(x, _)| x + 1).max().unwrap_or(0);
+    let height = grid.keys().map(|(_, y)| y + 1).max().unwrap_or(0);
+    let mut frame = Frame::new(width as usize, height as usize);
+    for (xy, tile) in grid {
+        let (glyph, color) = match tile {
+            Tile::Wall => ('#', Color::GRAY),
+            Tile::Box => ('O', Color::YELLOW),
+            Tile::BoxLeft => ('[', Color::YELLOW),
+            Tile::BoxRight => (']', Color::YELLOW),
+        };
+        frame.entity(*xy, glyph, color);
+    }
+    frame.entity(pos, '@', Color::RED);
+    frame
+}
+
 fn part_one(input: &str) -> i32 {
     let Input {
         mut grid,
@@ -205,6 +225,7 @@ fn part_one(input: &str) -> i32 {
             }
             MoveOutcome::Blocked => (),
         }
+        viz::emit(|| render(&grid, pos));
     }
 
     sum_of_coords(&grid)
@@ -234,6 +255,7 @@ fn part_two(input: &str) -> i32 {
             }
             MoveOutcome::Blocked => (),
         }
+        viz::emit(|| render(&grid, pos));
     }
 
     sum_of_coords(&grid)
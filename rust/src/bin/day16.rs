@@ -1,5 +1,11 @@
-use aoc24::aoc;
-use fxhash::FxHashSet;
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use aoc24::{
+    aoc,
+    bitset::VisitedSet,
+    viz::{self, Color, Frame},
+};
+use itertools::Either;
 
 struct Labyrinth {
     grid: Vec<char>,
@@ -16,7 +22,23 @@ enum Direction {
     West,
 }
 
+const DIRECTIONS: [Direction; 4] = [
+    Direction::North,
+    Direction::East,
+    Direction::South,
+    Direction::West,
+];
+
 impl Direction {
+    pub fn opposite(self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::East => Direction::West,
+            Direction::West => Direction::East,
+        }
+    }
+
     pub fn turns(self) -> [Direction; 2] {
         match self {
             Direction::North => [Direction::West, Direction::East],
@@ -29,17 +51,6 @@ impl Direction {
 
 type Node = (usize, Direction);
 impl Labyrinth {
-    // I bet this could be done smarter and guide astar better
-    pub fn heuristic(&self, idx: usize) -> usize {
-        let x = idx % self.width;
-        let y = idx / self.width;
-        let endx = self.end % self.width;
-        let endy = self.end / self.width;
-        let dx = x as f32 - endx as f32;
-        let dy = y as f32 - endy as f32;
-        (dx * dx + dy * dy).sqrt() as usize
-    }
-
     pub fn neighbor(&self, idx: usize, dir: Direction) -> usize {
         let x = idx % self.width;
         let y = idx / self.width;
@@ -63,6 +74,79 @@ impl Labyrinth {
             )
             .filter(|((idx, _), _)| self.grid[*idx] != '#')
     }
+
+    // Nodes from which a single move leads to `node`. Turning is symmetric, so the turns
+    // leading to a direction are the turns from it.
+    pub fn predecessors<'a>(&'a self, node: &Node) -> impl Iterator<Item = (Node, usize)> + 'a {
+        let (idx, dir) = *node;
+        let from = self.neighbor(idx, dir.opposite());
+        std::iter::once(((from, dir), 1))
+            .chain(dir.turns().map(|turned| ((from, turned), 1001)))
+            .filter(|((idx, _), _)| self.grid[*idx] != '#')
+    }
+
+    /// Dijkstra from every node of `starts`, indexed by `idx * 4 + direction`. With
+    /// `reverse`, moves are followed backwards, giving the cost to reach the starts instead.
+    pub fn costs(&self, starts: &[Node], reverse: bool) -> Vec<usize> {
+        let mut costs = vec![usize::MAX; self.grid.len() * DIRECTIONS.len()];
+        let mut settled = VisitedSet::new(self.grid.len(), DIRECTIONS.len());
+        let mut queue = BinaryHeap::new();
+        for (idx, dir) in starts {
+            costs[idx * 4 + *dir as usize] = 0;
+            queue.push(Reverse((0, *idx, *dir as usize)));
+        }
+        while let Some(Reverse((cost, idx, dir))) = queue.pop() {
+            if !settled.insert(idx, dir) {
+                continue;
+            }
+            let node = (idx, DIRECTIONS[dir]);
+            let moves = match reverse {
+                false => Either::Left(self.neighbors(&node)),
+                true => Either::Right(self.predecessors(&node)),
+            };
+            for ((next, next_dir), step) in moves {
+                let next_cost = cost + step;
+                let slot = &mut costs[next * 4 + next_dir as usize];
+                if next_cost < *slot {
+                    *slot = next_cost;
+                    queue.push(Reverse((next_cost, next, next_dir as usize)));
+                }
+            }
+        }
+        costs
+    }
+
+    fn cost_at(&self, costs: &[usize], idx: usize) -> usize {
+        DIRECTIONS
+            .iter()
+            .map(|dir| costs[idx * 4 + *dir as usize])
+            .min()
+            .expect("four directions")
+    }
+
+    /// Cost of the best paths, and the tiles that are part of at least one of them.
+    pub fn best_paths(&self) -> (usize, Vec<usize>) {
+        let from_start = self.costs(&[(self.start, Direction::East)], false);
+        let to_end = self.costs(&DIRECTIONS.map(|dir| (self.end, dir)), true);
+        let best = self.cost_at(&from_start, self.end);
+        let tiles = (0..self.grid.len())
+            .filter(|idx| {
+                (idx * 4..idx * 4 + 4).any(|s| from_start[s].saturating_add(to_end[s]) == best)
+            })
+            .collect();
+        (best, tiles)
+    }
+
+    pub fn render(&self, path: impl IntoIterator<Item = usize>) -> Frame {
+        let height = self.grid.len() / self.width;
+        let mut frame = Frame::new(self.width, height);
+        let xy = |idx: usize| ((idx % self.width) as i32, (idx / self.width) as i32);
+        for (idx, c) in self.grid.iter().enumerate() {
+            frame.set(xy(idx), *c);
+        }
+        frame.path(path.into_iter().map(xy), Color::GREEN);
+        frame
+    }
 }
 
 fn parse(input: &str) -> Labyrinth {
@@ -90,33 +174,20 @@ fn parse(input: &str) -> Labyrinth {
 
 fn part_one(input: &str) -> usize {
     let laby = parse(input);
-    let start = (laby.start, Direction::East);
-    let (_path, cost) = pathfinding::directed::astar::astar(
-        &start,
-        |node| laby.neighbors(node),
-        |(idx, _)| laby.heuristic(*idx),
-        |(idx, _)| laby.end == *idx,
-    )
-    .expect("Failed to find path");
+    let costs = laby.costs(&[(laby.start, Direction::East)], false);
+    let cost = laby.cost_at(&costs, laby.end);
+    assert!(cost != usize::MAX, "Failed to find path");
+    // Every best path, part one doesn't keep track of a single one
+    viz::emit(|| laby.render(laby.best_paths().1));
     cost
 }
 
 fn part_two(input: &str) -> usize {
     let laby = parse(input);
-    let start = (laby.start, Direction::East);
-    let (all_path, _) = pathfinding::directed::astar::astar_bag(
-        &start,
-        |node| laby.neighbors(node),
-        |(idx, _)| laby.heuristic(*idx),
-        |(idx, _)| laby.end == *idx,
-    )
-    .expect("Failed to find path");
-
-    all_path
-        .into_iter()
-        .flat_map(|path| path.into_iter().map(|(idx, _dir)| idx))
-        .collect::<FxHashSet<_>>()
-        .len()
+    let (cost, tiles) = laby.best_paths();
+    assert!(cost != usize::MAX, "Failed to find path");
+    viz::emit(|| laby.render(tiles.iter().copied()));
+    tiles.len()
 }
 
 aoc!(part_one, part_two);
@@ -141,9 +212,29 @@ pub mod tests {
 #S..#.....#...#
 ###############";
 
+    const INPUT_2: &str = "#################
+#...#...#...#..E#
+#.#.#.#.#.#.#.#.#
+#.#.#.#...#...#.#
+#.#.#.#.###.#.#.#
+#...#.#.#.....#.#
+#.#.#.#.#.#####.#
+#.#...#.#.#.....#
+#.#.#####.#.###.#
+#.#.#.......#...#
+#.#.###.#####.###
+#.#.#...#.....#.#
+#.#.#.#####.###.#
+#.#.#.........#.#
+#.#.#.#########.#
+#S#.............#
+#################";
+
     #[test]
     fn day16() {
         assert_eq!(part_one(INPUT), 7036);
         assert_eq!(part_two(INPUT), 45);
+        assert_eq!(part_one(INPUT_2), 11048);
+        assert_eq!(part_two(INPUT_2), 64);
     }
 }
@@ -1,4 +1,7 @@
-use aoc24::aoc;
+use aoc24::{
+    aoc,
+    viz::{self, Color, Frame},
+};
 use pathfinding::directed::dijkstra::dijkstra;
 use std::collections::HashSet;
 
@@ -28,9 +31,8 @@ fn part_two(input: &str) -> String {
 
 const NEIGHBORS: [(i32, i32); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
 
-fn solve_part_one(input: &str, grid_size: i32, take: usize) -> usize {
-    let bytes: HashSet<(i32, i32)> = parse(input).into_iter().take(take).collect();
-    let (_, cost) = dijkstra(
+fn shortest_path(bytes: &HashSet<(i32, i32)>, grid_size: i32) -> Option<(Vec<(i32, i32)>, usize)> {
+    dijkstra(
         &(0, 0),
         |(x, y)| {
             let mut nodes: Vec<((i32, i32), usize)> = Vec::with_capacity(4);
@@ -48,7 +50,22 @@ fn solve_part_one(input: &str, grid_size: i32, take: usize) -> usize {
         },
         |(x, y)| *x == grid_size && *y == grid_size,
     )
-    .expect("Failed to find path");
+}
+
+fn render(bytes: &HashSet<(i32, i32)>, grid_size: i32, path: &[(i32, i32)]) -> Frame {
+    let size = grid_size as usize + 1;
+    let mut frame = Frame::new(size, size);
+    for byte in bytes {
+        frame.set(*byte, '#');
+    }
+    frame.path(path.iter().copied(), Color::GREEN);
+    frame
+}
+
+fn solve_part_one(input: &str, grid_size: i32, take: usize) -> usize {
+    let bytes: HashSet<(i32, i32)> = parse(input).into_iter().take(take).collect();
+    let (path, cost) = shortest_path(&bytes, grid_size).expect("Failed to find path");
+    viz::emit(|| render(&bytes, grid_size, &path));
     cost
 }
 
@@ -64,25 +81,13 @@ fn solve_part_two(input: &str, grid_size: i32) -> (i32, i32) {
         }
         let idx = (max - min) / 2 + min;
         let bytes: HashSet<_> = all_bytes.iter().take(idx + 1).copied().collect();
-        let has_path = dijkstra(
-            &(0, 0),
-            |(x, y)| {
-                let mut nodes: Vec<((i32, i32), usize)> = Vec::with_capacity(4);
-                for (dx, dy) in NEIGHBORS {
-                    let x = x + dx;
-                    let y = y + dy;
-                    if (0..=grid_size).contains(&x)
-                        && (0..=grid_size).contains(&y)
-                        && !bytes.contains(&(x, y))
-                    {
-                        nodes.push(((x, y), 1));
-                    }
-                }
-                nodes
-            },
-            |(x, y)| *x == grid_size && *y == grid_size,
-        )
-        .is_some();
+        let path = shortest_path(&bytes, grid_size);
+        // One frame per step of the search, without a path once it is blocked
+        viz::emit(|| {
+            let path = path.as_ref().map(|(path, _)| path.as_slice());
+            render(&bytes, grid_size, path.unwrap_or_default())
+        });
+        let has_path = path.is_some();
         if max - min == 1 {
             if has_path {
                 break min;
@@ -1,13 +1,14 @@
-use fxhash::FxHashMap;
-
-use aoc24::aoc;
+use aoc24::{
+    aoc,
+    memo::{Memo, MemoStats},
+};
 
 struct Input<'a> {
     patterns: Vec<&'a str>,
     designs: Vec<&'a str>,
 }
 
-fn parse(input: &str) -> Input {
+fn parse(input: &str) -> Input<'_> {
     let mut input = input.split("\n\n");
 
     let patterns = input.next().expect("two elements").split(", ").collect();
@@ -17,52 +18,57 @@ fn parse(input: &str) -> Input {
 }
 
 fn solve_design<'a>(
-    memo: &mut FxHashMap<&'a str, usize>,
+    memo: &mut Memo<&'a str, usize>,
     design: &'a str,
     patterns: &[&'a str],
 ) -> usize {
-    if let Some(memoed) = memo.get(design) {
-        return *memoed;
-    }
-
-    let solved = patterns
-        .iter()
-        .map(|p| {
-            if !design.starts_with(p) {
-                return 0;
-            }
-            if design.len() == p.len() {
-                1
-            } else {
-                solve_design(memo, &design[p.len()..], patterns)
-            }
-        })
-        .sum();
-    memo.insert(design, solved);
-    solved
+    memo.recurse(design, &|rec, design| {
+        patterns
+            .iter()
+            .map(|p| {
+                if !design.starts_with(p) {
+                    return 0;
+                }
+                if design.len() == p.len() {
+                    1
+                } else {
+                    rec.call(&design[p.len()..])
+                }
+            })
+            .sum()
+    })
 }
 
-fn part_one(input: &str) -> usize {
+// Number of ways to make each design, and how well the memo did
+fn arrangements(input: &str) -> (Vec<usize>, MemoStats) {
     let Input { patterns, designs } = parse(input);
 
-    let mut memo = FxHashMap::default();
-    designs
+    let mut memo = Memo::new();
+    let ways = designs
+        .into_iter()
+        .map(|design| solve_design(&mut memo, design, &patterns))
+        .collect();
+    (ways, memo.stats())
+}
+
+fn part_one(input: &str) -> usize {
+    arrangements(input)
+        .0
         .into_iter()
-        .filter(|design| solve_design(&mut memo, design, &patterns) > 0)
+        .filter(|ways| *ways > 0)
         .count()
 }
 
 fn part_two(input: &str) -> usize {
-    let Input { patterns, designs } = parse(input);
+    arrangements(input).0.into_iter().sum()
+}
 
-    let mut memo = FxHashMap::default();
-    designs
-        .into_iter()
-        .map(|design| solve_design(&mut memo, design, &patterns))
-        .sum()
+fn memo_stats(input: &str) -> String {
+    let (ways, stats) = arrangements(input);
+    format!("{} designs: {stats}", ways.len())
 }
 
-aoc!(part_one, part_two);
+aoc!(part_one, part_two; memo_stats);
 
 #[cfg(test)]
 pub mod tests {
@@ -84,4 +90,12 @@ bbrgwb";
         assert_eq!(part_one(INPUT), 6);
         assert_eq!(part_two(INPUT), 16);
     }
+
+    #[test]
+    fn test_memo_stats() {
+        let (ways, stats) = arrangements(INPUT);
+        assert_eq!(ways, vec![2, 1, 4, 6, 0, 1, 2, 0]);
+        assert!(stats.hits > 0);
+        assert!(memo_stats(INPUT).starts_with("8 designs: "));
+    }
 }
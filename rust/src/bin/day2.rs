@@ -1,24 +1,182 @@
+use std::{fmt::Write, ops::RangeInclusive};
+
 use aoc24::aoc;
 
+// Allowed difference between two adjacent levels
+const STEPS: RangeInclusive<i32> = 1..=3;
+
 #[derive(Clone)]
 struct Report {
     levels: Vec<i32>,
 }
 
 impl Report {
-    pub fn safe(&self) -> bool {
+    pub fn safe(&self, steps: &RangeInclusive<i32>) -> bool {
         let cmp = self.levels[0].cmp(&self.levels[1]);
         self.levels
             .iter()
             .zip(self.levels.iter().skip(1))
-            .all(|(a, b)| a.cmp(b) == cmp && (1..=3).contains(&i32::abs(a - b)))
+            .all(|(a, b)| a.cmp(b) == cmp && steps.contains(&i32::abs(a - b)))
+    }
+
+    /// Whether removing at most `k` levels makes the report safe, in O(n * k).
+    pub fn safe_with_tolerance(&self, k: usize, steps: &RangeInclusive<i32>) -> bool {
+        assert!(*steps.start() > 0, "Steps must be strictly monotone");
+        if self.levels.len() <= k + 1 {
+            return true;
+        }
+        [1, -1]
+            .into_iter()
+            .any(|sign| self.min_removals(sign, k, steps) <= k)
+    }
+
+    // Fewest removals for the levels to move in the direction of `sign` by allowed steps.
+    // Two levels kept next to each other can't be more than `k + 1` apart, so only that window
+    // is looked at.
+    fn min_removals(&self, sign: i32, k: usize, steps: &RangeInclusive<i32>) -> usize {
+        let levels = &self.levels;
+        let n = levels.len();
+        // ending_at[i]: fewest removals in levels[..=i], level i being kept
+        let mut ending_at = vec![0; n];
+        for i in 0..n {
+            ending_at[i] = (i.saturating_sub(k + 1)..i)
+                .filter(|&j| steps.contains(&((levels[i] - levels[j]) * sign)))
+                .map(|j| ending_at[j] + i - j - 1)
+                .fold(i, usize::min);
+        }
+        (0..n).map(|i| ending_at[i] + n - 1 - i).min().unwrap_or(0)
+    }
+
+    /// The first pair of adjacent levels that makes the report unsafe. Reports with fewer
+    /// than two levels are safe, as for [`Report::safe_with_tolerance`].
+    pub fn first_violation(&self, steps: &RangeInclusive<i32>) -> Option<Violation> {
+        if self.levels.len() < 2 {
+            return None;
+        }
+        let direction = (self.levels[1] - self.levels[0]).signum();
+        self.levels
+            .windows(2)
+            .enumerate()
+            .find_map(|(index, pair)| {
+                let step = pair[1] - pair[0];
+                let rule = if step == 0 {
+                    Rule::ZeroStep
+                } else if step.signum() != direction {
+                    Rule::DirectionChange
+                } else if step.abs() > *steps.end() {
+                    Rule::StepTooLarge
+                } else if step.abs() < *steps.start() {
+                    Rule::StepTooSmall
+                } else {
+                    return None;
+                };
+                Some(Violation { index, rule })
+            })
+    }
+
+    /// The first level whose removal makes the report safe.
+    pub fn fixing_removal(&self, steps: &RangeInclusive<i32>) -> Option<usize> {
+        (0..self.levels.len()).find(|&idx| {
+            let mut report = self.clone();
+            report.levels.remove(idx);
+            report.levels.len() < 2 || report.safe(steps)
+        })
     }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rule {
+    DirectionChange,
+    ZeroStep,
+    StepTooLarge,
+    StepTooSmall,
+}
+
+impl Rule {
+    fn name(&self) -> &'static str {
+        match self {
+            Rule::DirectionChange => "direction_change",
+            Rule::ZeroStep => "zero_step",
+            Rule::StepTooLarge => "step_too_large",
+            Rule::StepTooSmall => "step_too_small",
+        }
+    }
+}
+
+/// Rule broken between levels `index` and `index + 1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Violation {
+    index: usize,
+    rule: Rule,
+}
+
+struct Diagnostic<'a> {
+    report: usize,
+    levels: &'a [i32],
+    violation: Violation,
+    fixed_by_removing: Option<usize>,
+}
+
+fn diagnose(reports: &[Report]) -> Vec<Diagnostic<'_>> {
+    reports
+        .iter()
+        .enumerate()
+        .filter_map(|(report, r)| {
+            let violation = r.first_violation(&STEPS)?;
+            Some(Diagnostic {
+                report,
+                levels: &r.levels,
+                violation,
+                fixed_by_removing: r.fixing_removal(&STEPS),
+            })
+        })
+        .collect()
+}
 
-    pub fn without(&self, idx: usize) -> Report {
-        let mut report = self.clone();
-        report.levels.remove(idx);
-        report
+// One line per unsafe report, report and level indices starting at 0
+fn explain(input: &str) -> String {
+    let reports = parse(input);
+    let mut text = String::new();
+    for d in diagnose(&reports) {
+        let Violation { index, rule } = d.violation;
+        let (a, b) = (d.levels[index], d.levels[index + 1]);
+        let fix = match d.fixed_by_removing {
+            Some(idx) => format!("safe without level {idx}"),
+            None => "unsafe with any single removal".to_string(),
+        };
+        writeln!(
+            text,
+            "report {}: {} at levels {index}-{} ({a} -> {b}), {fix}",
+            d.report,
+            rule.name(),
+            index + 1,
+        )
+        .expect("String write");
     }
+    text
+}
+
+fn explain_json(input: &str) -> String {
+    let reports = parse(input);
+    let entries: Vec<String> = diagnose(&reports)
+        .into_iter()
+        .map(|d| {
+            let levels: Vec<String> = d.levels.iter().map(i32::to_string).collect();
+            let fixed_by_removing = match d.fixed_by_removing {
+                Some(idx) => idx.to_string(),
+                None => "null".to_string(),
+            };
+            format!(
+                r#"{{"report":{},"levels":[{}],"index":{},"rule":"{}","fixed_by_removing":{}}}"#,
+                d.report,
+                levels.join(","),
+                d.violation.index,
+                d.violation.rule.name(),
+                fixed_by_removing
+            )
+        })
+        .collect();
+    format!("[{}]", entries.join(","))
 }
 
 fn parse(input: &str) -> Vec<Report> {
@@ -36,27 +194,25 @@ fn parse(input: &str) -> Vec<Report> {
 
 fn part_one(input: &str) -> usize {
     let reports = parse(input);
-    reports.iter().filter(|r| r.safe()).count()
+    reports.iter().filter(|r| r.safe(&STEPS)).count()
 }
 
-// It ain't stupid if it works.
 fn part_two(input: &str) -> usize {
     let reports = parse(input);
     reports
         .iter()
-        .filter(|r| {
-            (0..r.levels.len())
-                .map(|idx| r.without(idx))
-                .any(|r| r.safe())
-                || r.safe()
-        })
+        .filter(|r| r.safe_with_tolerance(1, &STEPS))
         .count()
 }
 
-aoc!(part_one, part_two);
+aoc!(part_one, part_two; explain, explain_json);
 
 #[cfg(test)]
 pub mod tests {
+    use itertools::Itertools;
+
+    use aoc24::rng::XorShift;
+
     use super::*;
 
     const INPUT: &str = "7 6 4 2 1
@@ -71,4 +227,76 @@ pub mod tests {
         assert_eq!(part_one(INPUT), 2);
         assert_eq!(part_two(INPUT), 4);
     }
+
+    #[test]
+    fn day2_explain() {
+        assert_eq!(
+            explain(INPUT),
+            "report 1: step_too_large at levels 1-2 (2 -> 7), unsafe with any single removal
+report 2: step_too_large at levels 2-3 (6 -> 2), unsafe with any single removal
+report 3: direction_change at levels 1-2 (3 -> 2), safe without level 1
+report 4: zero_step at levels 2-3 (4 -> 4), safe without level 2
+"
+        );
+        assert!(explain_json(INPUT).starts_with(
+            r#"[{"report":1,"levels":[1,2,7,8,9],"index":1,"rule":"step_too_large","fixed_by_removing":null},"#
+        ));
+        assert_eq!(
+            Report {
+                levels: vec![5, 5, 6]
+            }
+            .first_violation(&STEPS),
+            Some(Violation {
+                index: 0,
+                rule: Rule::ZeroStep
+            })
+        );
+        let single = Report { levels: vec![5] };
+        assert_eq!(single.first_violation(&STEPS), None);
+        assert!(single.safe_with_tolerance(0, &STEPS));
+        assert_eq!(
+            explain("5\n1 2 3\n1 5"),
+            "report 2: step_too_large at levels 0-1 (1 -> 5), safe without level 0\n"
+        );
+    }
+
+    // Tries every way of removing up to k levels
+    fn safe_brute_force(report: &Report, k: usize, steps: &RangeInclusive<i32>) -> bool {
+        let n = report.levels.len();
+        (0..=k.min(n)).any(|removed| {
+            (0..n).combinations(removed).any(|indices| {
+                let levels: Vec<i32> = (0..n)
+                    .filter(|i| !indices.contains(i))
+                    .map(|i| report.levels[i])
+                    .collect();
+                levels.len() < 2 || Report { levels }.safe(steps)
+            })
+        })
+    }
+
+    #[test]
+    fn day2_tolerance_matches_brute_force() {
+        let mut rng = XorShift::new(0x9e3779b97f4a7c15);
+
+        for _ in 0..5000 {
+            let len = 1 + rng.below(9) as usize;
+            let levels = (0..len).map(|_| rng.below(12) as i32).collect();
+            let report = Report { levels };
+            let k = rng.below(4) as usize;
+            let start = 1 + rng.below(2) as i32;
+            let steps = start..=start + rng.below(4) as i32;
+            assert_eq!(
+                report.safe_with_tolerance(k, &steps),
+                safe_brute_force(&report, k, &steps),
+                "{:?} k={k} steps={steps:?}",
+                report.levels
+            );
+            if report.levels.len() >= 2 {
+                assert_eq!(
+                    report.safe(&steps),
+                    report.first_violation(&steps).is_none()
+                );
+            }
+        }
+    }
 }
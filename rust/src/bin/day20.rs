@@ -1,55 +1,43 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, VecDeque};
 
-use aoc24::aoc;
-use maplit::hashset;
+use aoc24::{
+    aoc,
+    bitset::VisitedSet,
+    geometry::BoundingBox,
+    grid::SparseGrid,
+    viz::{self, Color, Frame},
+};
 
 struct Input {
     start: (i32, i32),
     end: (i32, i32),
-    walls: HashSet<(i32, i32)>,
-    limit_x: i32,
-    limit_y: i32,
+    walls: SparseGrid<()>,
 }
 
 fn parse(input: &str) -> Input {
     let mut start = (0, 0);
     let mut end = (0, 0);
-    let mut walls = HashSet::default();
-    let mut limit_x = 0;
-    let mut limit_y = 0;
-
-    for (y, line) in input.lines().enumerate() {
-        for (x, c) in line.chars().enumerate() {
-            match c {
-                '.' => (),
-                '#' => {
-                    walls.insert((x as i32, y as i32));
-                }
-                'S' => {
-                    start = (x as i32, y as i32);
-                }
-                'E' => {
-                    end = (x as i32, y as i32);
-                }
-                _ => panic!("Unexpected char {c}"),
-            }
-            limit_x = limit_x.max(x as i32);
+
+    let walls = SparseGrid::parse(input, |pos, c| match c {
+        '.' => None,
+        '#' => Some(()),
+        'S' => {
+            start = pos;
+            None
         }
-        limit_y = y as i32;
-    }
+        'E' => {
+            end = pos;
+            None
+        }
+        _ => panic!("Unexpected char {c}"),
+    });
 
-    Input {
-        start,
-        end,
-        walls,
-        limit_x,
-        limit_y,
-    }
+    Input { start, end, walls }
 }
 
 fn part_one(input: &str) -> usize {
     let input = parse(input);
-    let cheats = all_cheats(input);
+    let cheats = all_cheats(&input);
     cheats
         .into_iter()
         .filter_map(|(time_saved, how_much)| (time_saved >= 100).then_some(how_much))
@@ -58,57 +46,115 @@ fn part_one(input: &str) -> usize {
 
 const NEIGHBORS: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
 
-fn shortest_path_cheating_at(input: &Input, cheat_at: HashSet<(i32, i32)>) -> usize {
-    let Input {
-        start,
-        end,
-        walls,
-        limit_x,
-        limit_y,
-    } = input;
-
-    let successors = |node: &(i32, i32)| {
-        NEIGHBORS
-            .iter()
-            .filter_map(|delta| {
-                let pos = (node.0 + delta.0, node.1 + delta.1);
-                if pos.0 < 0
-                    || pos.1 < 0
-                    || pos.0 >= *limit_x
-                    || pos.1 >= *limit_y
-                    || (walls.contains(&pos) && !cheat_at.contains(&pos))
-                {
-                    return None;
+/// Breadth first search over the track. Its buffers are reused from one cheat to the next,
+/// the visited set being cleared in constant time.
+struct Search {
+    bounds: BoundingBox<2>,
+    walls: Vec<bool>,
+    visited: VisitedSet,
+    parents: Vec<(i32, i32)>,
+    queue: VecDeque<(i32, i32)>,
+}
+
+impl Search {
+    fn new(walls: &SparseGrid<()>) -> Self {
+        let bounds = walls.bounds().expect("non empty map");
+        let is_wall = bounds.points().map(|p| walls.contains(p)).collect();
+        Search {
+            bounds,
+            walls: is_wall,
+            visited: walls.visited_set(1),
+            parents: vec![(0, 0); bounds.volume()],
+            queue: VecDeque::new(),
+        }
+    }
+
+    // Walls can be crossed at `cheat_at` only
+    fn shortest_path(
+        &mut self,
+        start: (i32, i32),
+        end: (i32, i32),
+        cheat_at: Option<(i32, i32)>,
+    ) -> Option<Vec<(i32, i32)>> {
+        let index = |pos: (i32, i32)| self.bounds.index(pos);
+        self.visited.clear();
+        self.queue.clear();
+        self.visited.insert(index(start)?, 0);
+        self.queue.push_back(start);
+        while let Some(pos) = self.queue.pop_front() {
+            if pos == end {
+                let mut path = vec![end];
+                while let Some(&pos) = path.last().filter(|pos| **pos != start) {
+                    path.push(self.parents[index(pos).expect("in bounds")]);
                 }
-                Some((pos, 1))
-            })
-            .collect::<Vec<_>>()
-    };
-    let heuristic = |node: &(i32, i32)| {
-        let dx = node.0 as f32 - end.0 as f32;
-        let dy = node.1 as f32 - end.1 as f32;
-        (dx * dx + dy * dy).sqrt() as usize
-    };
-    let success = |node: &(i32, i32)| node == end;
-
-    let (_, cost) = pathfinding::directed::astar::astar(start, successors, heuristic, success)
+                path.reverse();
+                return Some(path);
+            }
+            for delta in NEIGHBORS {
+                let next = (pos.0 + delta.0, pos.1 + delta.1);
+                let Some(idx) = index(next) else { continue };
+                if self.walls[idx] && cheat_at != Some(next) {
+                    continue;
+                }
+                if self.visited.insert(idx, 0) {
+                    self.parents[idx] = pos;
+                    self.queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+}
+
+fn shortest_path_cheating_at(
+    input: &Input,
+    search: &mut Search,
+    cheat_at: Option<(i32, i32)>,
+) -> (Vec<(i32, i32)>, usize) {
+    let path = search
+        .shortest_path(input.start, input.end, cheat_at)
         .expect("Failed to find solution");
+    let cost = path.len() - 1;
+    (path, cost)
+}
 
-    cost
+fn render(input: &Input, path: &[(i32, i32)], cheat_at: Option<(i32, i32)>) -> Frame {
+    let bounds = input.walls.bounds().expect("non empty map");
+    let mut frame = Frame::new(bounds.width(), bounds.height());
+    for wall in input.walls.positions() {
+        frame.set(wall.into(), '#');
+    }
+    frame.path(path.iter().copied(), Color::GREEN);
+    frame.entity(input.start, 'S', Color::YELLOW);
+    frame.entity(input.end, 'E', Color::YELLOW);
+    if let Some(cheat) = cheat_at {
+        frame.highlight([cheat], Color::RED);
+    }
+    frame
 }
 
 // picoseconds saved -> nb of cheats
-fn all_cheats(input: Input) -> HashMap<usize, usize> {
-    let no_cheating = shortest_path_cheating_at(&input, HashSet::default());
+fn all_cheats(input: &Input) -> HashMap<usize, usize> {
+    let mut search = Search::new(&input.walls);
+    let (path, no_cheating) = shortest_path_cheating_at(input, &mut search, None);
+    viz::emit(|| render(input, &path, None));
     let mut cheats: HashMap<usize, usize> = HashMap::default();
-    for wall in &input.walls {
-        let best_path = shortest_path_cheating_at(&input, hashset![*wall]);
+    // The cheat saving the most time, to show it once done
+    let mut best_cheat = None;
+    for wall in input.walls.positions() {
+        let (path, best_path) = shortest_path_cheating_at(input, &mut search, Some(wall.into()));
         if best_path >= no_cheating {
             continue;
         }
         let saved = no_cheating - best_path;
         let entry = cheats.entry(saved).or_default();
         *entry += 1;
+        if best_cheat.as_ref().is_none_or(|(best, _, _)| saved > *best) {
+            best_cheat = Some((saved, wall.into(), path));
+        }
+    }
+    if let Some((_, wall, path)) = best_cheat {
+        viz::emit(|| render(input, &path, Some(wall)));
     }
 
     cheats
@@ -142,7 +188,7 @@ pub mod tests {
     fn day20() {
         let input = parse(INPUT);
         assert_eq!(
-            all_cheats(input),
+            all_cheats(&input),
             hashmap! {
                 2 => 14,
                 4 => 14,
@@ -1,90 +1,396 @@
+use std::{fmt::Write, ops::Range};
+
 use aoc24::aoc;
+use fxhash::FxHashMap;
 use nom::{
     branch::alt,
-    bytes::complete::tag,
-    character::complete::anychar,
-    combinator::map,
-    multi::{many0, many_till},
-    IResult, Parser,
+    bytes::complete::{tag, take_while_m_n},
+    character::complete::{anychar, char, satisfy},
+    combinator::{consumed, map, map_res, not, recognize},
+    multi::{many0, many1_count, many_till, separated_list0},
+    sequence::terminated,
+    IResult,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Arg<'a> {
+    Number(i64),
+    Register(&'a str),
+    Call(Call<'a>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Call<'a> {
+    name: &'a str,
+    args: Vec<Arg<'a>>,
+}
+
+/// An instruction the parser recognises. Control instructions run even when the machine is
+/// disabled, others are skipped. Arithmetic overflows give no value.
+struct Instruction {
+    name: &'static str,
+    arity: usize,
+    control: bool,
+    exec: fn(&mut Machine, &[Arg]) -> Option<i64>,
+}
+
+const MUL: Instruction = Instruction {
+    name: "mul",
+    arity: 2,
+    control: false,
+    exec: |m, args| m.value(&args[0])?.checked_mul(m.value(&args[1])?),
+};
+
+const DO: Instruction = Instruction {
+    name: "do",
+    arity: 0,
+    control: true,
+    exec: |m, _| {
+        m.enabled = true;
+        None
+    },
+};
+
+const DONT: Instruction = Instruction {
+    name: "don't",
+    arity: 0,
+    control: true,
+    exec: |m, _| {
+        m.enabled = false;
+        None
+    },
+};
+
+const ADD: Instruction = Instruction {
+    name: "add",
+    arity: 2,
+    control: false,
+    exec: |m, args| m.value(&args[0])?.checked_add(m.value(&args[1])?),
+};
+
+const SUB: Instruction = Instruction {
+    name: "sub",
+    arity: 2,
+    control: false,
+    exec: |m, args| m.value(&args[0])?.checked_sub(m.value(&args[1])?),
+};
+
+const SET: Instruction = Instruction {
+    name: "set",
+    arity: 2,
+    control: false,
+    exec: |m, args| {
+        let Arg::Register(register) = args[0] else {
+            return None;
+        };
+        let value = m.value(&args[1])?;
+        m.registers.insert(register.to_string(), value);
+        None
+    },
+};
+
+/// Instruction table, and whether arguments can be registers and nested calls on top of
+/// numbers.
+struct Language {
+    instructions: &'static [Instruction],
+    expressions: bool,
+}
+
+// The actual puzzle
+const PUZZLE: &Language = &Language {
+    instructions: &[MUL, DO, DONT],
+    expressions: false,
+};
+
+const EXTENDED: &Language = &Language {
+    instructions: &[MUL, DO, DONT, ADD, SUB, SET],
+    expressions: true,
 };
 
+fn parse_register(input: &str) -> IResult<&str, &str> {
+    terminated(
+        recognize(many1_count(satisfy(|c| c.is_ascii_lowercase()))),
+        not(char('(')),
+    )(input)
+}
+
+fn parse_arg<'a>(lang: &Language, input: &'a str) -> IResult<&'a str, Arg<'a>> {
+    if !lang.expressions {
+        // Puzzle operands are 1 to 3 digits
+        let digits = take_while_m_n(1, 3, |c: char| c.is_ascii_digit());
+        return map(map_res(digits, str::parse), Arg::Number)(input);
+    }
+    alt((
+        map(nom::character::complete::i64, Arg::Number),
+        map(|i| parse_call(lang, i), Arg::Call),
+        map(parse_register, Arg::Register),
+    ))(input)
+}
+
+fn parse_call<'a>(lang: &Language, input: &'a str) -> IResult<&'a str, Call<'a>> {
+    for instruction in lang.instructions {
+        let Some(rest) = input
+            .strip_prefix(instruction.name)
+            .and_then(|rest| rest.strip_prefix('('))
+        else {
+            continue;
+        };
+        let Ok((rest, args)) = separated_list0(tag(","), |i| parse_arg(lang, i))(rest) else {
+            continue;
+        };
+        let Some(rest) = rest.strip_prefix(')') else {
+            continue;
+        };
+        if args.len() != instruction.arity {
+            continue;
+        }
+        let name = &input[..instruction.name.len()];
+        return Ok((rest, Call { name, args }));
+    }
+    Err(nom::Err::Error(nom::error::Error::new(
+        input,
+        nom::error::ErrorKind::Tag,
+    )))
+}
+
+/// A top level call, with its position in the source.
 #[derive(Debug, PartialEq)]
-struct MulToken {
-    a: i32,
-    b: i32,
+struct Spanned<'a> {
+    span: Range<usize>,
+    call: Call<'a>,
+}
+
+fn parse_next_call<'a>(
+    lang: &Language,
+    source: &'a str,
+    input: &'a str,
+) -> IResult<&'a str, Spanned<'a>> {
+    let (rest, (_garbage, (text, call))) =
+        many_till(map(anychar, drop), consumed(|i| parse_call(lang, i)))(input)?;
+    let end = source.len() - rest.len();
+    let start = end - text.len();
+    Ok((
+        rest,
+        Spanned {
+            span: start..end,
+            call,
+        },
+    ))
+}
+
+fn parse<'a>(lang: &Language, input: &'a str) -> Vec<Spanned<'a>> {
+    let (_leftover, calls) =
+        many0(|i| parse_next_call(lang, input, i))(input).expect("Failed to parse input");
+    calls
 }
 
-enum Token {
-    Mul(MulToken),
-    Do,
-    Dont,
+// Hand-written scanner for the puzzle language, equivalent to `parse(PUZZLE, input)`.
+// Only `m` and `d` can start an instruction, so it jumps from one to the next.
+fn scan(input: &str) -> Vec<Spanned<'_>> {
+    let bytes = input.as_bytes();
+    let mut calls = Vec::new();
+    let mut pos = 0;
+    while let Some(offset) = bytes[pos..].iter().position(|b| *b == b'm' || *b == b'd') {
+        let start = pos + offset;
+        let rest = &bytes[start..];
+        let found = if let Some(operands) = rest.strip_prefix(b"mul(") {
+            scan_mul(operands).map(|(a, b, len)| {
+                let call = Call {
+                    name: "mul",
+                    args: vec![Arg::Number(a), Arg::Number(b)],
+                };
+                (call, 4 + len)
+            })
+        } else if rest.starts_with(b"do()") {
+            let call = Call {
+                name: "do",
+                args: Vec::new(),
+            };
+            Some((call, 4))
+        } else if rest.starts_with(b"don't()") {
+            let call = Call {
+                name: "don't",
+                args: Vec::new(),
+            };
+            Some((call, 7))
+        } else {
+            None
+        };
+
+        match found {
+            Some((call, len)) => {
+                calls.push(Spanned {
+                    span: start..start + len,
+                    call,
+                });
+                pos = start + len;
+            }
+            None => pos = start + 1,
+        }
+    }
+    calls
 }
 
-fn parse_mul_token(input: &str) -> IResult<&str, MulToken> {
-    let (input, _mul) = tag("mul(")(input)?;
-    let (input, a) = nom::character::complete::i32(input)?;
-    let (input, _comma) = tag(",")(input)?;
-    let (input, b) = nom::character::complete::i32(input)?;
-    let (input, _mul_end) = tag(")")(input)?;
-    Ok((input, MulToken { a, b }))
+// `a,b)` with 1 to 3 digits operands, returns the operands and the length read
+fn scan_mul(bytes: &[u8]) -> Option<(i64, i64, usize)> {
+    let (a, a_len) = scan_number(bytes)?;
+    let bytes = bytes[a_len..].strip_prefix(b",")?;
+    let (b, b_len) = scan_number(bytes)?;
+    bytes[b_len..]
+        .starts_with(b")")
+        .then_some((a, b, a_len + b_len + 2))
 }
 
-fn parse_next_token(input: &str) -> IResult<&str, Token> {
-    let parse_mul_token = parse_mul_token.map(Token::Mul);
-    let parse_do = tag("do()").map(|_| Token::Do);
-    let parse_dont = tag("don't()").map(|_| Token::Dont);
-    let parse_token = alt((parse_mul_token, parse_do, parse_dont));
-    let (input, (_garbage, token)) = many_till(map(anychar, drop), parse_token)(input)?;
-    Ok((input, token))
+fn scan_number(bytes: &[u8]) -> Option<(i64, usize)> {
+    let digits = bytes
+        .iter()
+        .take(3)
+        .take_while(|b| b.is_ascii_digit())
+        .count();
+    let number = bytes[..digits]
+        .iter()
+        .fold(0, |n, b| n * 10 + (b - b'0') as i64);
+    (digits > 0).then_some((number, digits))
 }
 
-fn parse(input: &str) -> IResult<&str, Vec<Token>> {
-    let (input, token) = many0(parse_next_token)(input)?;
-    Ok((input, token))
+struct Machine<'t> {
+    lang: &'t Language,
+    enabled: bool,
+    registers: FxHashMap<String, i64>,
 }
 
-fn part_one(input: &str) -> i32 {
-    let (_leftover, tokens) = parse(input).expect("Failed to parse input");
-    tokens
-        .into_iter()
-        .filter_map(|tok| match tok {
-            Token::Mul(MulToken { a, b }) => Some(a * b),
-            Token::Do | Token::Dont => None,
-        })
-        .sum()
+impl Machine<'_> {
+    fn value(&mut self, arg: &Arg) -> Option<i64> {
+        match arg {
+            Arg::Number(n) => Some(*n),
+            Arg::Register(register) => Some(self.registers.get(*register).copied().unwrap_or(0)),
+            Arg::Call(call) => self.call(call),
+        }
+    }
+
+    fn call(&mut self, call: &Call) -> Option<i64> {
+        let instruction = self.instruction(call.name);
+        (instruction.exec)(self, &call.args)
+    }
+
+    fn instruction(&self, name: &str) -> &Instruction {
+        self.lang
+            .instructions
+            .iter()
+            .find(|i| i.name == name)
+            .expect("Parsed calls are in the table")
+    }
 }
 
-fn part_two(input: &str) -> i32 {
-    let (_leftover, tokens) = parse(input).expect("Failed to parse input");
+#[derive(Debug, PartialEq)]
+enum Status {
+    Counted(i64),
+    Disabled,
+    NoValue,
+    /// The value doesn't fit in the total, and isn't counted
+    Overflow(i64),
+}
 
-    let (_enabled, total) = tokens
-        .into_iter()
-        .fold((true, 0), |(enabled, total), tok| match tok {
-            Token::Do => (true, total),
-            Token::Dont => (false, total),
-            Token::Mul(MulToken { a, b }) if enabled => (enabled, total + a * b),
-            Token::Mul(MulToken { .. }) => (enabled, total),
-        });
-    total
+struct Executed {
+    span: Range<usize>,
+    status: Status,
 }
 
-aoc!(part_one, part_two);
+// Runs every top level call, adding up the values of the enabled ones
+fn run(lang: &Language, calls: Vec<Spanned>, use_enable: bool) -> (i64, Vec<Executed>) {
+    let mut machine = Machine {
+        lang,
+        enabled: true,
+        registers: FxHashMap::default(),
+    };
+    let mut total: i64 = 0;
+    let mut executed = Vec::new();
+    for Spanned { span, call } in calls {
+        let control = machine.instruction(call.name).control;
+        let status = if use_enable && !machine.enabled && !control {
+            Status::Disabled
+        } else {
+            match machine.call(&call) {
+                Some(value) => match total.checked_add(value) {
+                    Some(sum) => {
+                        total = sum;
+                        Status::Counted(value)
+                    }
+                    None => Status::Overflow(value),
+                },
+                None => Status::NoValue,
+            }
+        };
+        executed.push(Executed { span, status });
+    }
+    (total, executed)
+}
+
+fn part_one(input: &str) -> i64 {
+    run(PUZZLE, scan(input), false).0
+}
+
+fn part_two(input: &str) -> i64 {
+    run(PUZZLE, scan(input), true).0
+}
+
+fn part_one_nom(input: &str) -> i64 {
+    run(PUZZLE, parse(PUZZLE, input), false).0
+}
+
+fn part_two_nom(input: &str) -> i64 {
+    run(PUZZLE, parse(PUZZLE, input), true).0
+}
+
+fn annotated_listing(lang: &Language, input: &str) -> String {
+    let (total, executed) = run(lang, parse(lang, input), true);
+    let mut listing = String::new();
+    for Executed { span, status } in executed {
+        let status = match status {
+            Status::Counted(value) => format!("+{value}"),
+            Status::Disabled => "disabled".to_string(),
+            Status::NoValue => String::new(),
+            Status::Overflow(value) => format!("+{value} overflows"),
+        };
+        let fragment = &input[span.clone()];
+        let line = format!("{:>6}..{:<6} {fragment:<24} {status}", span.start, span.end);
+        writeln!(listing, "{}", line.trim_end()).expect("String write");
+    }
+    writeln!(listing, "total: {total}").expect("String write");
+    listing
+}
+
+fn listing(input: &str) -> String {
+    annotated_listing(PUZZLE, input)
+}
+
+fn listing_extended(input: &str) -> String {
+    annotated_listing(EXTENDED, input)
+}
+
+aoc!(part_one, part_two, part_one_nom, part_two_nom; listing, listing_extended);
 
 #[cfg(test)]
 pub mod tests {
+    use aoc24::rng::XorShift;
+
     use super::*;
 
+    fn mul(a: i64, b: i64) -> Call<'static> {
+        Call {
+            name: "mul",
+            args: vec![Arg::Number(a), Arg::Number(b)],
+        }
+    }
+
     #[test]
     fn test_parse_mul_token() {
-        assert_eq!(
-            parse_mul_token("mul(10,25)"),
-            Ok(("", MulToken { a: 10, b: 25 }))
-        );
-
-        assert_eq!(
-            parse_mul_token("mul(1,2)az3"),
-            Ok(("az3", MulToken { a: 1, b: 2 }))
-        );
+        assert_eq!(parse_call(PUZZLE, "mul(10,25)"), Ok(("", mul(10, 25))));
+        assert_eq!(parse_call(PUZZLE, "mul(1,2)az3"), Ok(("az3", mul(1, 2))));
+        assert!(parse_call(PUZZLE, "mul(1,2,3)").is_err());
+        assert!(parse_call(PUZZLE, "add(1,2)").is_err());
+        assert!(parse_call(PUZZLE, "mul(1234,2)").is_err());
+        assert!(parse_call(PUZZLE, "mul(-1,2)").is_err());
     }
 
     const INPUT: &str = "xmul(2,4)%&mul[3,7]!@^do_not_mul(5,5)+mul(32,64]then(mul(11,8)mul(8,5))";
@@ -95,5 +401,113 @@ pub mod tests {
     fn day3() {
         assert_eq!(part_one(INPUT), 161);
         assert_eq!(part_two(INPUT_TWO), 48);
+        assert_eq!(part_one_nom(INPUT), 161);
+        assert_eq!(part_two_nom(INPUT_TWO), 48);
+    }
+
+    #[test]
+    fn day3_scan_matches_nom() {
+        let mut rng = XorShift::new(0x853c49e6748fea9b);
+        const FRAGMENTS: [&str; 16] = [
+            "mul(1,2)",
+            "mul(12,345)",
+            "mul(",
+            "mu",
+            "do()",
+            "don't()",
+            "do(",
+            ")",
+            ",",
+            "m",
+            "d",
+            "7",
+            "42",
+            "999",
+            "1234",
+            "é",
+        ];
+
+        let mut muls = 0;
+        for _ in 0..2000 {
+            let input: String = (0..rng.below(40) as usize)
+                .map(|_| FRAGMENTS[rng.below(16) as usize])
+                .collect();
+            let calls = scan(&input);
+            muls += calls.iter().filter(|c| c.call.name == "mul").count();
+            assert_eq!(calls, parse(PUZZLE, &input), "{input}");
+        }
+        assert!(muls > 50, "Random inputs should contain instructions");
+    }
+
+    #[test]
+    fn day3_spans() {
+        let calls = parse(PUZZLE, INPUT_TWO);
+        assert_eq!(calls.len(), 6);
+        assert_eq!(calls[0].span, 1..9);
+        assert_eq!(&INPUT_TWO[calls[1].span.clone()], "don't()");
+        assert_eq!(calls[5].call, mul(8, 5));
+
+        let calls = parse(PUZZLE, "é€mul(1,2)");
+        assert_eq!(calls[0].span, 5..13);
+    }
+
+    #[test]
+    fn day3_extended() {
+        let input = "set(x,5)%mul(mul(1,2),3)don't()add(x,1)do()!sub(x,1)set(x,add(x,x))mul(x,y)";
+        let (total, executed) = run(EXTENDED, parse(EXTENDED, input), true);
+        let statuses: Vec<Status> = executed.into_iter().map(|e| e.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                Status::NoValue,
+                Status::Counted(6),
+                Status::NoValue,
+                Status::Disabled,
+                Status::NoValue,
+                Status::Counted(4),
+                Status::NoValue,
+                Status::Counted(0),
+            ]
+        );
+        assert_eq!(total, 10);
+        // The puzzle ignores the extensions, nested calls included
+        assert_eq!(part_one(input), 2);
+
+        // Any i64 is a valid operand, overflowing gives no value
+        let input =
+            "mul(9999999999,9999999999)add(9223372036854775807,1)sub(0,9223372036854775807)\
+            sub(sub(0,9223372036854775807),2)mul(3037000499,3037000499)mul(3037000499,3037000499)\
+            mul(3037000499,3037000499)";
+        let (total, executed) = run(EXTENDED, parse(EXTENDED, input), true);
+        let statuses: Vec<Status> = executed.into_iter().map(|e| e.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                Status::NoValue,
+                Status::NoValue,
+                Status::Counted(-9223372036854775807),
+                Status::NoValue,
+                Status::Counted(9223372030926249001),
+                Status::Counted(9223372030926249001),
+                Status::Overflow(9223372030926249001),
+            ]
+        );
+        assert_eq!(
+            total,
+            9223372030926249001 - 9223372036854775807 + 9223372030926249001
+        );
+        assert!(listing_extended("mul(9999999999,9999999999)").ends_with("total: 0\n"));
+    }
+
+    #[test]
+    fn day3_listing() {
+        assert_eq!(
+            listing("xmul(2,4)don't()mul(5,5)"),
+            "     1..9      mul(2,4)                 +8
+     9..16     don't()
+    16..24     mul(5,5)                 disabled
+total: 8
+"
+        );
     }
 }
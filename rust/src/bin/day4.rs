@@ -1,84 +1,31 @@
-use std::collections::HashMap;
-
-use aoc24::aoc;
-
-fn parse(input: &str) -> HashMap<(i32, i32), char> {
-    input
-        .lines()
-        .enumerate()
-        .flat_map(|(y, line)| {
-            line.chars()
-                .enumerate()
-                .map(move |(x, c)| ((x as i32, y as i32), c))
-        })
-        .collect()
+use aoc24::{
+    aoc,
+    grid::SparseGrid,
+    word_search::{Template, WordSearch},
+};
+
+fn parse(input: &str) -> SparseGrid<char> {
+    SparseGrid::parse(input, |_, c| Some(c))
 }
 
-const DIRECTIONS: [(i32, i32); 8] = [
-    (-1, -1),
-    (-1, 0),
-    (-1, 1),
-    (0, -1),
-    (0, 1),
-    (1, -1),
-    (1, 0),
-    (1, 1),
-];
-
-// Need to redo this, but I can't think of anything smarter rn
 fn part_one(input: &str) -> usize {
     let grid = parse(input);
-    let get = |x: i32, y: i32| grid.get(&(x, y));
-    let get_words_starting_from_pos = |x: i32, y: i32| {
-        DIRECTIONS.iter().map(move |&(dx, dy)| {
-            (0..4)
-                .filter_map(move |d| get(x + dx * d, y + dy * d))
-                .collect::<String>()
-        })
-    };
-    grid.iter()
-        .filter_map(|((x, y), c)| {
-            if *c == 'X' {
-                Some(get_words_starting_from_pos(*x, *y))
-            } else {
-                None
-            }
-        })
-        .flatten()
-        .filter(|word| word == "XMAS")
-        .count()
+    WordSearch::new(&grid).count(&["XMAS"])
 }
 
-const DIRECTIONS_PART_TWO: [(i32, i32); 4] = [(1, 1), (-1, 1), (1, -1), (-1, -1)];
+// The grid with every XMAS highlighted
+fn highlight(input: &str) -> String {
+    let grid = parse(input);
+    let search = WordSearch::new(&grid);
+    search.render(&search.find(&["XMAS"])).to_ansi()
+}
 
-// Need to redo this, but I can't think of anything smarter rn
 fn part_two(input: &str) -> usize {
     let grid = parse(input);
-    let get = |x: i32, y: i32| grid.get(&(x, y));
-    let get_words_starting_from_pos = |x: i32, y: i32| -> Vec<String> {
-        DIRECTIONS_PART_TWO
-            .iter()
-            .map(move |&(dx, dy)| {
-                (0..3)
-                    .filter_map(move |d| get(x + dx - dx * d, y + dy - dy * d))
-                    .collect::<String>()
-            })
-            .collect()
-    };
-
-    grid.iter()
-        .filter(|((x, y), c)| {
-            if **c == 'A' {
-                let words = get_words_starting_from_pos(*x, *y);
-                words.iter().filter(|&w| w == "MAS").count() == 2
-            } else {
-                false
-            }
-        })
-        .count()
+    WordSearch::new(&grid).count_template(&Template::parse("M.S\n.A.\nM.S"))
 }
 
-aoc!(part_one, part_two);
+aoc!(part_one, part_two; highlight);
 
 #[cfg(test)]
 pub mod tests {
@@ -1,4 +1,8 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::{Display, Write},
+};
 
 use aoc24::aoc;
 
@@ -66,30 +70,353 @@ fn update_safe(dependencies: &HashMap<i32, HashSet<i32>>, update: &[i32]) -> boo
     })
 }
 
-fn fixed_update(dependencies: &HashMap<i32, HashSet<i32>>, update: &[i32]) -> Vec<i32> {
+#[derive(Debug, PartialEq, Eq)]
+struct UpdateOrder {
+    pages: Vec<i32>,
+    /// Whether no other order satisfies the rules, the middle page being ambiguous otherwise
+    unique: bool,
+}
+
+/// Pages that can't be ordered, each one having to come before the next, and the last one
+/// before the first. Starts from the smallest page.
+#[derive(Debug, PartialEq, Eq)]
+struct Cycle(Vec<i32>);
+
+impl Display for Cycle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for page in &self.0 {
+            write!(f, "{page} -> ")?;
+        }
+        write!(f, "{}", self.0[0])
+    }
+}
+
+// Kahn's algorithm over the rules between pages of the update
+fn topological_order(
+    dependencies: &HashMap<i32, HashSet<i32>>,
+    update: &[i32],
+) -> Result<UpdateOrder, Cycle> {
     let open: HashSet<i32> = update.iter().copied().collect();
+    let deps = |x: &i32| {
+        dependencies
+            .get(x)
+            .into_iter()
+            .flatten()
+            .filter(|d| open.contains(d))
+    };
 
-    let deps: HashMap<i32, HashSet<i32>> = open
+    let mut missing: HashMap<i32, usize> = HashMap::default();
+    let mut dependents: HashMap<i32, Vec<i32>> = HashMap::default();
+    for x in update {
+        missing.insert(*x, deps(x).count());
+        for d in deps(x) {
+            dependents.entry(*d).or_default().push(*x);
+        }
+    }
+
+    let mut ready: VecDeque<i32> = update.iter().copied().filter(|x| missing[x] == 0).collect();
+    let mut pages = Vec::with_capacity(update.len());
+    let mut unique = true;
+    while let Some(x) = ready.pop_front() {
+        unique &= ready.is_empty();
+        pages.push(x);
+        for next in dependents.get(&x).into_iter().flatten() {
+            let count = missing.get_mut(next).expect("Page in update");
+            *count -= 1;
+            if *count == 0 {
+                ready.push_back(*next);
+            }
+        }
+    }
+
+    if pages.len() == missing.len() {
+        return Ok(UpdateOrder { pages, unique });
+    }
+
+    // Every page left waits on another page left, so going up the dependencies loops
+    let mut chain = vec![*update.iter().find(|x| missing[x] > 0).expect("Page left")];
+    loop {
+        let last = chain[chain.len() - 1];
+        let dep = *deps(&last)
+            .filter(|d| missing[d] > 0)
+            .min()
+            .expect("Page left has a dependency left");
+        if let Some(start) = chain.iter().position(|x| *x == dep) {
+            let mut cycle = chain.split_off(start);
+            cycle.reverse();
+            let smallest = (0..cycle.len()).min_by_key(|i| cycle[*i]).unwrap_or(0);
+            cycle.rotate_left(smallest);
+            return Err(Cycle(cycle));
+        }
+        chain.push(dep);
+    }
+}
+
+// One line per update: whether its order is unique, ambiguous or impossible
+fn orderings(input: &str) -> String {
+    let Input {
+        dependencies,
+        updates,
+    } = parse(input);
+    let mut text = String::new();
+    for (idx, update) in updates.iter().enumerate() {
+        let status = match topological_order(&dependencies, update) {
+            Ok(UpdateOrder { unique: true, .. }) => "unique".to_string(),
+            Ok(UpdateOrder { unique: false, .. }) => "ambiguous".to_string(),
+            Err(cycle) => format!("cycle {cycle}"),
+        };
+        writeln!(text, "update {idx}: {status}").expect("String write");
+    }
+    text
+}
+
+// Every rule as `(before, after)`, sorted
+fn rules(dependencies: &HashMap<i32, HashSet<i32>>) -> Vec<(i32, i32)> {
+    let mut rules: Vec<(i32, i32)> = dependencies
         .iter()
-        .map(|x| match dependencies.get(x) {
-            None => (*x, HashSet::<i32>::default()),
-            Some(deps) => (*x, open.intersection(deps).copied().collect()),
-        })
+        .flat_map(|(after, befores)| befores.iter().map(|before| (*before, *after)))
         .collect();
+    rules.sort();
+    rules
+}
+
+// Rules between pages of the update
+fn update_rules(dependencies: &HashMap<i32, HashSet<i32>>, update: &[i32]) -> Vec<(i32, i32)> {
+    let open: HashSet<i32> = update.iter().copied().collect();
+    rules(dependencies)
+        .into_iter()
+        .filter(|(a, b)| open.contains(a) && open.contains(b))
+        .collect()
+}
+
+fn pages(rules: &[(i32, i32)]) -> Vec<i32> {
+    let mut pages: Vec<i32> = rules.iter().flat_map(|(a, b)| [*a, *b]).collect();
+    pages.sort();
+    pages.dedup();
+    pages
+}
+
+fn successors(rules: &[(i32, i32)]) -> HashMap<i32, Vec<i32>> {
+    let mut successors: HashMap<i32, Vec<i32>> = HashMap::default();
+    for (a, b) in rules {
+        successors.entry(*a).or_default().push(*b);
+    }
+    successors
+}
 
-    let mut close: HashSet<i32> = HashSet::default();
-    let mut fixed = Vec::new();
-    while fixed.len() != update.len() {
-        for x in &open - &close {
-            let deps = &deps[&x];
-            if (deps - &close).is_empty() {
-                fixed.push(x);
-                close.insert(x);
+/// The rules that are not implied by other rules. Only meaningful for acyclic rules.
+fn transitive_reduction(rules: &[(i32, i32)]) -> Vec<(i32, i32)> {
+    let successors = successors(rules);
+    // Pages reachable from a page, through at least one rule
+    let reachable = |from: i32| {
+        let mut seen: HashSet<i32> = HashSet::default();
+        let mut stack = vec![from];
+        while let Some(x) = stack.pop() {
+            for next in successors.get(&x).into_iter().flatten() {
+                if seen.insert(*next) {
+                    stack.push(*next);
+                }
             }
         }
+        seen
+    };
+    let reachable: HashMap<i32, HashSet<i32>> = pages(rules)
+        .into_iter()
+        .map(|p| (p, reachable(p)))
+        .collect();
+
+    rules
+        .iter()
+        .copied()
+        .filter(|(a, b)| {
+            !successors[a]
+                .iter()
+                .any(|c| c != b && reachable[c].contains(b))
+        })
+        .collect()
+}
+
+/// Strongly connected components with more than one page, with Tarjan's algorithm. Pages and
+/// components are sorted.
+fn cyclic_components(rules: &[(i32, i32)]) -> Vec<Vec<i32>> {
+    struct Tarjan<'a> {
+        successors: &'a HashMap<i32, Vec<i32>>,
+        index: HashMap<i32, usize>,
+        low: HashMap<i32, usize>,
+        stack: Vec<i32>,
+        on_stack: HashSet<i32>,
+        components: Vec<Vec<i32>>,
+    }
+
+    impl Tarjan<'_> {
+        fn visit(&mut self, x: i32) {
+            let index = self.index.len();
+            self.index.insert(x, index);
+            self.low.insert(x, index);
+            self.stack.push(x);
+            self.on_stack.insert(x);
+            for next in self.successors.get(&x).into_iter().flatten() {
+                if !self.index.contains_key(next) {
+                    self.visit(*next);
+                    let low = self.low[&x].min(self.low[next]);
+                    self.low.insert(x, low);
+                } else if self.on_stack.contains(next) {
+                    let low = self.low[&x].min(self.index[next]);
+                    self.low.insert(x, low);
+                }
+            }
+            if self.low[&x] == self.index[&x] {
+                let mut component = Vec::new();
+                while let Some(y) = self.stack.pop() {
+                    self.on_stack.remove(&y);
+                    component.push(y);
+                    if y == x {
+                        break;
+                    }
+                }
+                self.components.push(component);
+            }
+        }
+    }
+
+    let successors = successors(rules);
+    let mut tarjan = Tarjan {
+        successors: &successors,
+        index: HashMap::default(),
+        low: HashMap::default(),
+        stack: Vec::new(),
+        on_stack: HashSet::default(),
+        components: Vec::new(),
+    };
+    for page in pages(rules) {
+        if !tarjan.index.contains_key(&page) {
+            tarjan.visit(page);
+        }
+    }
+    let mut components: Vec<Vec<i32>> = tarjan
+        .components
+        .into_iter()
+        .filter(|c| c.len() > 1)
+        .map(|mut c| {
+            c.sort();
+            c
+        })
+        .collect();
+    components.sort();
+    components
+}
+
+/// Rules broken by the update, the `after` page being printed before the `before` one.
+fn violations(dependencies: &HashMap<i32, HashSet<i32>>, update: &[i32]) -> Vec<(i32, i32)> {
+    update
+        .iter()
+        .enumerate()
+        .flat_map(|(i, x)| update[i + 1..].iter().map(move |y| (*y, *x)))
+        .filter(|(before, after)| dependencies.get(after).is_some_and(|d| d.contains(before)))
+        .collect()
+}
+
+fn to_dot(name: &str, rules: &[(i32, i32)]) -> String {
+    let mut dot = format!("digraph {name} {{\n");
+    for page in pages(rules) {
+        writeln!(dot, "    {page};").expect("String write");
+    }
+    for (a, b) in rules {
+        writeln!(dot, "    {a} -> {b};").expect("String write");
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+fn format_rules(rules: &[(i32, i32)]) -> String {
+    let rules: Vec<String> = rules.iter().map(|(a, b)| format!("{a}|{b}")).collect();
+    rules.join(", ")
+}
+
+// The rules implied by no others, for each update if the whole rule set is cyclic
+fn reduction(input: &str) -> String {
+    let Input {
+        dependencies,
+        updates,
+    } = parse(input);
+    let rules = rules(&dependencies);
+    let cycle = match topological_order(&dependencies, &pages(&rules)) {
+        Ok(_) => return format!("{}\n", format_rules(&transitive_reduction(&rules))),
+        Err(cycle) => cycle,
+    };
+    let mut text = format!("rules are cyclic ({cycle}), reducing each update\n");
+    for (idx, update) in updates.iter().enumerate() {
+        let rules = update_rules(&dependencies, update);
+        match topological_order(&dependencies, update) {
+            Ok(_) => writeln!(
+                text,
+                "update {idx}: {}",
+                format_rules(&transitive_reduction(&rules))
+            ),
+            Err(cycle) => writeln!(text, "update {idx}: cycle {cycle}"),
+        }
+        .expect("String write");
+    }
+    text
+}
+
+fn cycles(input: &str) -> String {
+    let Input { dependencies, .. } = parse(input);
+    let components = cyclic_components(&rules(&dependencies));
+    if components.is_empty() {
+        return "no cycles\n".to_string();
+    }
+    let mut text = String::new();
+    for component in components {
+        let cycle = topological_order(&dependencies, &component)
+            .expect_err("Strongly connected pages can't be ordered");
+        writeln!(
+            text,
+            "{} pages, e.g. {cycle}: {component:?}",
+            component.len()
+        )
+        .expect("String write");
+    }
+    text
+}
+
+// One line per unsafe update, with the rules it breaks
+fn explain(input: &str) -> String {
+    let Input {
+        dependencies,
+        updates,
+    } = parse(input);
+    let mut text = String::new();
+    for (idx, update) in updates.iter().enumerate() {
+        let broken = violations(&dependencies, update);
+        if !broken.is_empty() {
+            writeln!(text, "update {idx} breaks {}", format_rules(&broken)).expect("String write");
+        }
     }
+    text
+}
+
+fn dot(input: &str) -> String {
+    let Input { dependencies, .. } = parse(input);
+    to_dot("rules", &rules(&dependencies))
+}
 
-    fixed
+// One graph per update, only with the rules between its pages
+fn dot_updates(input: &str) -> String {
+    let Input {
+        dependencies,
+        updates,
+    } = parse(input);
+    updates
+        .iter()
+        .enumerate()
+        .map(|(idx, update)| {
+            to_dot(
+                &format!("update_{idx}"),
+                &update_rules(&dependencies, update),
+            )
+        })
+        .collect()
 }
 
 fn part_one(input: &str) -> i32 {
@@ -114,13 +441,125 @@ fn part_two(input: &str) -> i32 {
             if update_safe(&dependencies, update) {
                 return None;
             }
-            let fixed = fixed_update(&dependencies, update);
-            Some(middle(&fixed))
+            let ordering = topological_order(&dependencies, update)
+                .unwrap_or_else(|cycle| panic!("Failed to order {update:?}: {cycle}"));
+            Some(middle(&ordering.pages))
         })
         .sum()
 }
 
-aoc!(part_one, part_two);
+/// Rules as a dense bit matrix: bit `b` of `before[a]` is set when page `a` must come before
+/// page `b`. Only pages below 100 fit: rules with other pages are left out, and updates with
+/// other pages are left to the graph based functions.
+struct RuleMatrix {
+    before: [u128; 100],
+}
+
+impl RuleMatrix {
+    fn new(dependencies: &HashMap<i32, HashSet<i32>>) -> Self {
+        let mut before = [0; 100];
+        for (after, befores) in dependencies {
+            for before_page in befores {
+                if !(0..100).contains(before_page) || !(0..100).contains(after) {
+                    continue;
+                }
+                before[*before_page as usize] |= 1 << after;
+            }
+        }
+        RuleMatrix { before }
+    }
+
+    fn compare(&self, a: i32, b: i32) -> Ordering {
+        if self.before[a as usize] >> b & 1 == 1 {
+            Ordering::Less
+        } else if self.before[b as usize] >> a & 1 == 1 {
+            Ordering::Greater
+        } else {
+            Ordering::Equal
+        }
+    }
+
+    // Whether every page of the update has a row in the matrix
+    fn covers(&self, update: &[i32]) -> bool {
+        update
+            .iter()
+            .all(|p| (0..self.before.len() as i32).contains(p))
+    }
+
+    /// Whether the rules give a strict total order of the update's pages, which `sort_by`
+    /// needs to be correct: every two pages are ordered one way, and `a < b < c` implies
+    /// `a < c`. Always `false` for updates the matrix doesn't cover.
+    fn is_total_order(&self, update: &[i32]) -> bool {
+        if !self.covers(update) {
+            return false;
+        }
+        let pages: u128 = update.iter().fold(0, |pages, p| pages | 1 << p);
+        update.iter().all(|a| {
+            let after = self.before[*a as usize] & pages;
+            let before: u128 = update
+                .iter()
+                .filter(|b| self.before[**b as usize] >> a & 1 == 1)
+                .fold(0, |before, b| before | 1 << b);
+            after & before == 0
+                && (after | before | 1 << a) == pages
+                && update
+                    .iter()
+                    .filter(|b| after >> **b & 1 == 1)
+                    .all(|b| self.before[*b as usize] & pages & !after == 0)
+        })
+    }
+
+    /// Whether no page comes after a page it must be before, `None` for updates the matrix
+    /// doesn't cover.
+    fn is_sorted(&self, update: &[i32]) -> Option<bool> {
+        if !self.covers(update) {
+            return None;
+        }
+        let mut seen: u128 = 0;
+        Some(update.iter().all(|p| {
+            let ok = self.before[*p as usize] & seen == 0;
+            seen |= 1 << p;
+            ok
+        }))
+    }
+
+    /// The update sorted with the rules as comparator, `None` if they are not a total order
+    /// or the matrix doesn't cover the update.
+    fn sorted(&self, update: &[i32]) -> Option<Vec<i32>> {
+        if !self.is_total_order(update) {
+            return None;
+        }
+        let mut sorted = update.to_vec();
+        sorted.sort_by(|a, b| self.compare(*a, *b));
+        Some(sorted)
+    }
+}
+
+fn part_two_comparator(input: &str) -> i32 {
+    let Input {
+        dependencies,
+        updates,
+    } = parse(input);
+    let matrix = RuleMatrix::new(&dependencies);
+    updates
+        .iter()
+        .filter(|update| {
+            !matrix
+                .is_sorted(update)
+                .unwrap_or_else(|| update_safe(&dependencies, update))
+        })
+        .map(|update| {
+            let fixed = matrix.sorted(update).unwrap_or_else(|| {
+                topological_order(&dependencies, update)
+                    .unwrap_or_else(|cycle| panic!("Failed to order {update:?}: {cycle}"))
+                    .pages
+            });
+            middle(&fixed)
+        })
+        .sum()
+}
+
+aoc!(part_one, part_two, part_two_comparator; orderings, reduction, cycles, explain, dot, dot_updates);
 
 #[cfg(test)]
 pub mod tests {
@@ -169,4 +608,131 @@ pub mod tests {
         assert_eq!(part_one(INPUT), 143);
         assert_eq!(part_two(INPUT), 123);
     }
+
+    #[test]
+    fn day5_topological_order() {
+        let Input {
+            dependencies,
+            updates,
+        } = parse(INPUT);
+        assert_eq!(
+            topological_order(&dependencies, &updates[3]),
+            Ok(UpdateOrder {
+                pages: vec![97, 75, 47, 61, 53],
+                unique: true
+            })
+        );
+        assert!(orderings(INPUT).lines().all(|l| l.ends_with("unique")));
+
+        let Input {
+            dependencies,
+            updates,
+        } = parse("1|2\n2|3\n3|1\n4|1\n\n4,1,2,3\n4,5,1\n3,1");
+        assert_eq!(
+            topological_order(&dependencies, &updates[0]),
+            Err(Cycle(vec![1, 2, 3]))
+        );
+        assert_eq!(Cycle(vec![1, 2, 3]).to_string(), "1 -> 2 -> 3 -> 1");
+        assert_eq!(
+            topological_order(&dependencies, &updates[1]),
+            Ok(UpdateOrder {
+                pages: vec![4, 5, 1],
+                unique: false
+            })
+        );
+        assert_eq!(
+            topological_order(&dependencies, &updates[2]).map(|o| o.pages),
+            Ok(vec![3, 1])
+        );
+    }
+
+    #[test]
+    fn day5_analysis() {
+        let Input {
+            dependencies,
+            updates,
+        } = parse(INPUT);
+        let rules = rules(&dependencies);
+        assert_eq!(rules.len(), 21);
+        // The example rules are a total order, reduced to a chain
+        assert_eq!(
+            format_rules(&transitive_reduction(&rules)),
+            "29|13, 47|61, 53|29, 61|53, 75|47, 97|75"
+        );
+        assert!(cyclic_components(&rules).is_empty());
+        assert_eq!(cycles(INPUT), "no cycles\n");
+
+        assert_eq!(violations(&dependencies, &updates[0]), vec![]);
+        assert_eq!(violations(&dependencies, &updates[3]), vec![(97, 75)]);
+        assert_eq!(
+            explain(INPUT),
+            "update 3 breaks 97|75\nupdate 4 breaks 29|13\nupdate 5 breaks 75|13, 29|13, 47|13, 47|29\n"
+        );
+
+        assert_eq!(
+            to_dot("update", &update_rules(&dependencies, &updates[2])),
+            "digraph update {\n    13;\n    29;\n    75;\n    29 -> 13;\n    75 -> 13;\n    75 -> 29;\n}\n"
+        );
+    }
+
+    #[test]
+    fn day5_cycles() {
+        let input = "1|2\n2|3\n3|1\n3|4\n4|5\n5|4\n1|3\n\n1,2\n3,2,1";
+        let Input { dependencies, .. } = parse(input);
+        assert_eq!(
+            cyclic_components(&rules(&dependencies)),
+            vec![vec![1, 2, 3], vec![4, 5]]
+        );
+        assert_eq!(
+            reduction(input),
+            "rules are cyclic (1 -> 3 -> 1), reducing each update\nupdate 0: 1|2\nupdate 1: cycle 1 -> 3 -> 1\n"
+        );
+    }
+
+    #[test]
+    fn day5_comparator() {
+        assert_eq!(part_two_comparator(INPUT), 123);
+
+        let Input {
+            dependencies,
+            updates,
+        } = parse(INPUT);
+        let matrix = RuleMatrix::new(&dependencies);
+        assert!(updates.iter().all(|u| matrix.is_total_order(u)));
+        assert!(updates
+            .iter()
+            .all(|u| matrix.is_sorted(u) == Some(update_safe(&dependencies, u))));
+        assert_eq!(matrix.sorted(&updates[5]), Some(vec![97, 75, 47, 29, 13]));
+
+        // 5 is not ordered with the others, 1 2 3 form a cycle
+        let Input {
+            dependencies,
+            updates,
+        } = parse("4|1\n1|2\n2|3\n3|1\n\n1,5,4\n3,2,1\n1,2,4");
+        let matrix = RuleMatrix::new(&dependencies);
+        assert_eq!(matrix.sorted(&updates[0]), None);
+        assert_eq!(matrix.sorted(&updates[1]), None);
+        assert_eq!(matrix.sorted(&updates[2]), None);
+        assert_eq!(matrix.sorted(&[4, 1]), Some(vec![4, 1]));
+
+        // Pages out of the matrix go through the graph instead
+        let input = "1|2\n\n2,150,1\n1,2,-3";
+        let Input { dependencies, .. } = parse(input);
+        let matrix = RuleMatrix::new(&dependencies);
+        assert_eq!(matrix.is_sorted(&[2, 150, 1]), None);
+        assert_eq!(matrix.is_sorted(&[1, 2, -3]), None);
+        assert!(!matrix.is_total_order(&[2, 150, 1]));
+        assert_eq!(matrix.sorted(&[2, 150, 1]), None);
+        assert_eq!(part_two_comparator(input), part_two(input));
+        assert_eq!(part_two_comparator(input), 1);
+
+        // Rules with pages out of the matrix only matter to updates it doesn't cover
+        let input = "150|2\n2|3\n\n3,2,150\n3,2";
+        let Input { dependencies, .. } = parse(input);
+        let matrix = RuleMatrix::new(&dependencies);
+        assert_eq!(matrix.sorted(&[3, 2]), Some(vec![2, 3]));
+        assert_eq!(matrix.is_sorted(&[3, 2, 150]), None);
+        assert_eq!(part_two_comparator(input), part_two(input));
+        assert_eq!(part_two_comparator(input), 2 + 3);
+    }
 }
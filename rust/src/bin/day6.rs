@@ -1,39 +1,54 @@
-use std::collections::HashSet;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
 
-use aoc24::aoc;
+use rayon::prelude::*;
+
+use aoc24::{
+    aoc,
+    bitset::VisitedSet,
+    geometry::{BoundingBox, Point},
+    grid::SparseGrid,
+    viz::{self, Color, Frame},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Guard {
+    position: (i32, i32),
+    direction: Direction,
+}
 
 struct Map {
-    obstacles: HashSet<(i32, i32)>,
-    start_position: (i32, i32),
-    limit: (i32, i32),
+    obstacles: SparseGrid<()>,
+    /// In reading order
+    guards: Vec<Guard>,
+}
+
+impl Map {
+    /// The puzzle's only guard.
+    fn guard(&self) -> Guard {
+        assert_eq!(self.guards.len(), 1, "Expected a single guard");
+        self.guards[0]
+    }
 }
 
 fn parse(input: &str) -> Map {
-    let mut start_position: (i32, i32) = (0, 0);
-    let mut obstacles = HashSet::default();
-    let mut limit = (0, 0);
-
-    for (y, line) in input.lines().enumerate() {
-        for (x, c) in line.chars().enumerate() {
-            limit.0 = limit.0.max(x as i32);
-            match c {
-                '#' => {
-                    obstacles.insert((x as i32, y as i32));
-                }
-                '^' => {
-                    start_position = (x as i32, y as i32);
-                }
-                '.' => (),
-                c => panic!("Unsupported char {c}"),
-            }
+    let mut guards = Vec::new();
+    let obstacles = SparseGrid::parse(input, |position, c| match c {
+        '#' => Some(()),
+        '.' => None,
+        c => {
+            let direction =
+                Direction::from_glyph(c).unwrap_or_else(|| panic!("Unsupported char {c}"));
+            guards.push(Guard {
+                position,
+                direction,
+            });
+            None
         }
-        limit.1 = limit.1.max(y as i32);
-    }
-    Map {
-        obstacles,
-        start_position,
-        limit,
-    }
+    });
+    Map { obstacles, guards }
 }
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
@@ -54,6 +69,25 @@ impl Direction {
         }
     }
 
+    fn from_glyph(glyph: char) -> Option<Direction> {
+        match glyph {
+            '^' => Some(Direction::Top),
+            '>' => Some(Direction::Right),
+            'v' => Some(Direction::Down),
+            '<' => Some(Direction::Left),
+            _ => None,
+        }
+    }
+
+    fn glyph(&self) -> char {
+        match self {
+            Direction::Top => '^',
+            Direction::Right => '>',
+            Direction::Down => 'v',
+            Direction::Left => '<',
+        }
+    }
+
     fn turn_90deg(self) -> Direction {
         match self {
             Direction::Top => Direction::Right,
@@ -64,116 +98,464 @@ impl Direction {
     }
 }
 
-fn part_one(input: &str) -> usize {
-    let Map {
-        obstacles,
-        start_position,
-        limit,
-    } = parse(input);
+fn render(
+    obstacles: &SparseGrid<()>,
+    visited: &HashSet<(i32, i32)>,
+    position: (i32, i32),
+    direction: Direction,
+) -> Frame {
+    let bounds = obstacles.bounds().expect("non empty map");
+    let mut frame = Frame::new(bounds.width(), bounds.height());
+    for obstacle in obstacles.positions() {
+        frame.set(obstacle.into(), '#');
+    }
+    frame.path(visited.iter().copied(), Color::GREEN);
+    frame.entity(position, direction.glyph(), Color::RED);
+    frame
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Event {
+    Moved,
+    Turned,
+    /// The guard walks off the map from its current position
+    Exited,
+    /// The guard is back in a state it was already in
+    Looped,
+}
+
+/// Simulates a guard one action at a time, yielding its state after every move or turn.
+///
+/// The guard exits when the cell ahead is out of bounds, so the last position yielded is always
+/// on the map. The iterator ends after `Exited` or `Looped`, [`GuardSim::restart`] goes on after
+/// a loop.
+struct GuardSim<'a> {
+    obstacles: &'a SparseGrid<()>,
+    states: &'a mut VisitedSet,
+    guard: Guard,
+    done: bool,
+}
+
+impl<'a> GuardSim<'a> {
+    /// `states` is cleared, so it can be reused between simulations.
+    fn new(obstacles: &'a SparseGrid<()>, states: &'a mut VisitedSet, guard: Guard) -> Self {
+        states.clear();
+        let cell = obstacles
+            .index(guard.position)
+            .expect("guard within bounds");
+        states.insert(cell, guard.direction as usize);
+        GuardSim {
+            obstacles,
+            states,
+            guard,
+            done: false,
+        }
+    }
 
-    let mut visited: HashSet<(i32, i32)> = HashSet::default();
-    let mut direction = Direction::Top;
-    let mut position = start_position;
+    /// Keeps simulating after a loop, from the state the guard is in. The states seen so far
+    /// are forgotten, so the next `Looped` comes after one more lap.
+    fn restart(&mut self) {
+        self.states.clear();
+        let cell = self
+            .obstacles
+            .index(self.guard.position)
+            .expect("guard within bounds");
+        self.states.insert(cell, self.guard.direction as usize);
+        self.done = false;
+    }
+}
 
-    loop {
+impl Iterator for GuardSim<'_> {
+    type Item = ((i32, i32), Direction, Event);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let Guard {
+            position,
+            direction,
+        } = &mut self.guard;
         let (dx, dy) = direction.delta();
-        position.0 += dx;
-        position.1 += dy;
-        visited.insert(position);
+        let next = (position.0 + dx, position.1 + dy);
+
+        let event = if !self.obstacles.contains_in_bounds(next) {
+            Event::Exited
+        } else if self.obstacles.contains(next) {
+            *direction = direction.turn_90deg();
+            Event::Turned
+        } else {
+            *position = next;
+            Event::Moved
+        };
+
+        let event = match event {
+            Event::Exited => Event::Exited,
+            event => {
+                let cell = self
+                    .obstacles
+                    .index(*position)
+                    .expect("guard within bounds");
+                if self.states.insert(cell, *direction as usize) {
+                    event
+                } else {
+                    Event::Looped
+                }
+            }
+        };
+        self.done = matches!(event, Event::Exited | Event::Looped);
+        Some((*position, *direction, event))
+    }
+}
+
+fn part_one(input: &str) -> usize {
+    let map = parse(input);
+    let start = map.guard();
+    let obstacles = &map.obstacles;
+    let mut states = obstacles.visited_set(4);
+
+    let mut visited: HashSet<(i32, i32)> = HashSet::from([start.position]);
+    for (position, direction, event) in GuardSim::new(obstacles, &mut states, start) {
+        match event {
+            Event::Moved => {
+                visited.insert(position);
+            }
+            Event::Turned | Event::Exited => {
+                viz::emit(|| render(obstacles, &visited, position, direction));
+            }
+            Event::Looped => {}
+        }
+    }
+    visited.len()
+}
+
+fn can_escape(states: &mut VisitedSet, obstacles: &SparseGrid<()>, start: Guard) -> bool {
+    GuardSim::new(obstacles, states, start)
+        .last()
+        .is_some_and(|(_, _, event)| event == Event::Exited)
+}
+
+/// For every cell and direction, where the guard stops walking straight: the cell just before
+/// the next obstacle, or `None` when it leaves the map.
+struct JumpTable {
+    bounds: BoundingBox<2>,
+    stops: Vec<[Option<(i32, i32)>; 4]>,
+}
 
-        if position.0 < 0 || position.0 >= limit.0 || position.1 < 0 || position.1 >= limit.1 {
-            return visited.len();
+impl JumpTable {
+    fn new(obstacles: &SparseGrid<()>) -> Self {
+        let bounds = obstacles.bounds().expect("non empty map");
+        let mut stops = vec![[None; 4]; bounds.volume()];
+        let points: Vec<Point<2>> = bounds.points().collect();
+        for direction in [
+            Direction::Top,
+            Direction::Right,
+            Direction::Down,
+            Direction::Left,
+        ] {
+            let delta = Point::from(direction.delta());
+            // The cell ahead has to be filled first
+            let mut fill = |pos: &Point<2>| {
+                let next = *pos + delta;
+                let stop = if !bounds.contains(next) {
+                    None
+                } else if obstacles.contains(next) {
+                    Some((*pos).into())
+                } else {
+                    stops[bounds.index(next).expect("in bounds")][direction as usize]
+                };
+                stops[bounds.index(*pos).expect("in bounds")][direction as usize] = stop;
+            };
+            match direction {
+                Direction::Top | Direction::Left => points.iter().for_each(&mut fill),
+                Direction::Right | Direction::Down => points.iter().rev().for_each(fill),
+            }
         }
+        JumpTable { bounds, stops }
+    }
 
-        let lookahead = (position.0 + dx, position.1 + dy);
-        if obstacles.contains(&lookahead) {
-            direction = direction.turn_90deg();
+    /// Where the guard stops, taking an extra obstacle into account: only its row and column
+    /// differ from the table.
+    fn stop(
+        &self,
+        position: (i32, i32),
+        direction: Direction,
+        obstacle: (i32, i32),
+    ) -> Option<(i32, i32)> {
+        let index = self.bounds.index(position).expect("guard within bounds");
+        let stop = self.stops[index][direction as usize];
+        let (dx, dy) = direction.delta();
+        let (ox, oy) = (obstacle.0 - position.0, obstacle.1 - position.1);
+        let distance = ox * dx + oy * dy;
+        if ox * dy - oy * dx != 0 || distance <= 0 {
+            return stop;
+        }
+        match stop {
+            Some(s) if (s.0 - position.0) * dx + (s.1 - position.1) * dy < distance => stop,
+            _ => Some((obstacle.0 - dx, obstacle.1 - dy)),
         }
     }
 }
 
-fn can_escape(
-    mut visited: HashSet<((i32, i32), Direction)>,
-    obstacles: &HashSet<(i32, i32)>,
+// Same as `can_escape`, jumping from obstacle to obstacle. Only the turns are recorded.
+fn can_escape_jumping(
+    visited: &mut VisitedSet,
+    table: &JumpTable,
+    obstacle: (i32, i32),
     mut position: (i32, i32),
     mut direction: Direction,
-    limit: (i32, i32),
 ) -> bool {
-    loop {
-        loop {
-            if !visited.insert((position, direction)) {
-                // Guard in a loop
-                return false;
-            }
-            let (dx, dy) = direction.delta();
-            let lookahead = (position.0 + dx, position.1 + dy);
-            if !obstacles.contains(&lookahead) {
-                break;
-            }
-            direction = direction.turn_90deg();
+    visited.clear();
+    while let Some(stop) = table.stop(position, direction, obstacle) {
+        position = stop;
+        let cell = table.bounds.index(position).expect("guard within bounds");
+        if !visited.insert(cell, direction as usize) {
+            // Guard in a loop
+            return false;
         }
+        direction = direction.turn_90deg();
+    }
+    // Guard escaped
+    true
+}
 
-        let (dx, dy) = direction.delta();
-        position.0 += dx;
-        position.1 += dy;
+/// A cell of the guard's path where an obstacle could be put, and the state of the guard just
+/// before first walking into it. Everything up to that state is unchanged by the obstacle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Contact {
+    obstacle: (i32, i32),
+    position: (i32, i32),
+    direction: Direction,
+}
 
-        if position.0 < 0 || position.0 > limit.0 || position.1 < 0 || position.1 > limit.1 {
-            // Guard escaped
-            return true;
+// Every cell of the unobstructed walk but the start, in the order the guard first reaches them
+fn first_contacts(obstacles: &SparseGrid<()>, start: Guard) -> Vec<Contact> {
+    let mut states = obstacles.visited_set(4);
+    let mut seen: HashSet<(i32, i32)> = HashSet::from([start.position]);
+    let mut contacts = Vec::new();
+    let mut previous = start.position;
+    for (position, direction, event) in GuardSim::new(obstacles, &mut states, start) {
+        if event == Event::Moved && seen.insert(position) {
+            contacts.push(Contact {
+                obstacle: position,
+                position: previous,
+                direction,
+            });
         }
+        previous = position;
     }
+    contacts
+}
+
+// Obstructions making the guard loop, sorted
+fn loop_obstacles(map: &Map) -> Vec<(i32, i32)> {
+    let table = JumpTable::new(&map.obstacles);
+    let mut visited = map.obstacles.visited_set(4);
+    let mut obstacles: Vec<(i32, i32)> = first_contacts(&map.obstacles, map.guard())
+        .into_iter()
+        .filter(|c| !can_escape_jumping(&mut visited, &table, c.obstacle, c.position, c.direction))
+        .map(|c| c.obstacle)
+        .collect();
+    obstacles.sort();
+    obstacles
 }
 
 fn part_two(input: &str) -> usize {
-    let Map {
-        mut obstacles,
-        start_position,
-        limit,
-    } = parse(input);
+    loop_obstacles(&parse(input)).len()
+}
 
-    let mut path: HashSet<(i32, i32)> = HashSet::default();
-    let mut direction = Direction::Top;
-    let mut position = start_position;
+// Checking the obstacles on every core
+fn part_two_parallel(input: &str) -> usize {
+    let map = parse(input);
+    let obstacles = &map.obstacles;
 
-    loop {
-        let (dx, dy) = direction.delta();
-        position.0 += dx;
-        position.1 += dy;
-        path.insert(position);
+    let table = JumpTable::new(obstacles);
+    first_contacts(obstacles, map.guard())
+        .into_par_iter()
+        .map_init(
+            || obstacles.visited_set(4),
+            |visited, c| !can_escape_jumping(visited, &table, c.obstacle, c.position, c.direction),
+        )
+        .filter(|loops| *loops)
+        .count()
+}
 
-        if position.0 < 0 || position.0 >= limit.0 || position.1 < 0 || position.1 >= limit.1 {
-            break;
-        }
+// One obstruction per line, as `x,y`
+fn loop_positions(input: &str) -> String {
+    loop_obstacles(&parse(input))
+        .into_iter()
+        .map(|(x, y)| format!("{x},{y}\n"))
+        .collect()
+}
 
-        let lookahead = (position.0 + dx, position.1 + dy);
-        if obstacles.contains(&lookahead) {
-            direction = direction.turn_90deg();
-        }
-    }
+// Walking the guard cell by cell
+fn part_two_stepping(input: &str) -> usize {
+    let map = parse(input);
+    let start = map.guard();
+    let mut obstacles = map.obstacles;
 
+    let mut visited = obstacles.visited_set(4);
     let mut total = 0;
-    for (x, y) in path {
-        if !obstacles.insert((x, y)) {
-            continue;
-        }
-        if !can_escape(
-            HashSet::default(),
-            &obstacles,
-            start_position,
-            Direction::Top,
-            limit,
-        ) {
+    for Contact {
+        obstacle: (x, y), ..
+    } in first_contacts(&obstacles, start)
+    {
+        obstacles.insert((x, y), ());
+        if !can_escape(&mut visited, &obstacles, start) {
             total += 1;
         }
-        obstacles.remove(&(x, y));
+        obstacles.remove((x, y));
     }
     total
 }
 
-aoc!(part_one, part_two);
+/// How several guards fared, patrolling in lockstep.
+#[derive(Debug, PartialEq, Eq)]
+struct Patrols {
+    /// Distinct cells visited by each guard
+    visited: Vec<usize>,
+    /// Whether each guard left the map, or got stuck in a loop
+    exited: Vec<bool>,
+    /// Times two guards ended a tick on the same cell
+    collisions: usize,
+}
+
+// Every guard turns or moves once per tick, guards not being obstacles to each other. Guards
+// stuck in a loop keep walking it, until every other guard has left the map: only looping
+// guards are left then, and nothing new can happen.
+fn patrol_together(map: &Map) -> Patrols {
+    let obstacles = &map.obstacles;
+    let mut states: Vec<VisitedSet> = map
+        .guards
+        .iter()
+        .map(|_| obstacles.visited_set(4))
+        .collect();
+    let mut sims: Vec<GuardSim> = states
+        .iter_mut()
+        .zip(&map.guards)
+        .map(|(states, guard)| GuardSim::new(obstacles, states, *guard))
+        .collect();
+    let mut positions: Vec<(i32, i32)> = map.guards.iter().map(|g| g.position).collect();
+    let mut visited: Vec<HashSet<(i32, i32)>> =
+        positions.iter().map(|p| HashSet::from([*p])).collect();
+    let mut exited = vec![false; sims.len()];
+    let mut looping = vec![false; sims.len()];
+    let mut collisions = 0;
+
+    while exited.iter().zip(&looping).any(|(e, l)| !e && !l) {
+        for (idx, sim) in sims.iter_mut().enumerate() {
+            if exited[idx] {
+                continue;
+            }
+            let (position, _, event) = sim.next().expect("guard on the map");
+            match event {
+                Event::Moved => {
+                    visited[idx].insert(position);
+                }
+                Event::Turned => {}
+                Event::Exited => exited[idx] = true,
+                Event::Looped => {
+                    looping[idx] = true;
+                    sim.restart();
+                }
+            }
+            positions[idx] = position;
+        }
+
+        let on_map: Vec<(i32, i32)> = positions
+            .iter()
+            .zip(&exited)
+            .filter(|(_, exited)| !**exited)
+            .map(|(p, _)| *p)
+            .collect();
+        collisions += on_map
+            .iter()
+            .enumerate()
+            .map(|(i, a)| on_map[i + 1..].iter().filter(|b| *b == a).count())
+            .sum::<usize>();
+    }
+
+    Patrols {
+        visited: visited.iter().map(HashSet::len).collect(),
+        exited,
+        collisions,
+    }
+}
+
+fn guards(input: &str) -> String {
+    let map = parse(input);
+    let patrols = patrol_together(&map);
+    let mut text = String::new();
+    for (idx, guard) in map.guards.iter().enumerate() {
+        let (x, y) = guard.position;
+        let end = if patrols.exited[idx] {
+            "exited"
+        } else {
+            "loops"
+        };
+        writeln!(
+            text,
+            "guard {idx} ({} at {x},{y}): {} cells, {end}",
+            guard.direction.glyph(),
+            patrols.visited[idx]
+        )
+        .expect("String write");
+    }
+    writeln!(text, "collisions: {}", patrols.collisions).expect("String write");
+    text
+}
+
+// The map with the guards' paths drawn like in the puzzle: `|` and `-` where they walk up/down
+// and left/right, `+` where they do both or turn
+fn replay(input: &str) -> String {
+    let map = parse(input);
+    let obstacles = &map.obstacles;
+    let bounds = obstacles.bounds().expect("non empty map");
+    // Whether each cell is walked vertically and horizontally
+    let mut walked: HashMap<(i32, i32), (bool, bool)> = HashMap::default();
+    let mut mark = |position: (i32, i32), direction: Direction| {
+        let (vertical, horizontal) = walked.entry(position).or_default();
+        match direction {
+            Direction::Top | Direction::Down => *vertical = true,
+            Direction::Right | Direction::Left => *horizontal = true,
+        }
+    };
+
+    let mut states = obstacles.visited_set(4);
+    for guard in &map.guards {
+        mark(guard.position, guard.direction);
+        for (position, direction, event) in GuardSim::new(obstacles, &mut states, *guard) {
+            if matches!(event, Event::Moved | Event::Turned) {
+                mark(position, direction);
+            }
+        }
+    }
+
+    let mut frame = Frame::new(bounds.width(), bounds.height());
+    for obstacle in obstacles.positions() {
+        frame.set(obstacle.into(), '#');
+    }
+    for (position, walked) in walked {
+        let glyph = match walked {
+            (true, true) => '+',
+            (true, false) => '|',
+            (false, true) => '-',
+            (false, false) => continue,
+        };
+        frame.set(position, glyph);
+    }
+    for guard in &map.guards {
+        frame.set(guard.position, guard.direction.glyph());
+    }
+    frame.to_text()
+}
+
+aoc!(part_one, part_two, part_two_stepping, part_two_parallel; loop_positions, guards, replay);
 
 #[cfg(test)]
 pub mod tests {
+    use aoc24::rng::XorShift;
+
     use super::*;
 
     const INPUT: &str = "....#.....
@@ -191,5 +573,234 @@ pub mod tests {
     fn day6() {
         assert_eq!(part_one(INPUT), 41);
         assert_eq!(part_two(INPUT), 6);
+        assert_eq!(part_two_stepping(INPUT), 6);
+        assert_eq!(part_two_parallel(INPUT), 6);
+        assert_eq!(loop_positions(INPUT), "1,8\n3,6\n3,8\n6,7\n7,7\n7,9\n");
+    }
+
+    #[test]
+    fn day6_first_contacts() {
+        let map = parse(INPUT);
+        let contacts = first_contacts(&map.obstacles, map.guard());
+        assert_eq!(contacts.len(), 40);
+        assert_eq!(
+            contacts[0],
+            Contact {
+                obstacle: (4, 5),
+                position: (4, 6),
+                direction: Direction::Top
+            }
+        );
+        // Reached going up first, then crossed going left
+        let crossing = contacts.iter().find(|c| c.obstacle == (4, 3));
+        assert_eq!(crossing.map(|c| c.direction), Some(Direction::Top));
+    }
+
+    #[test]
+    fn day6_jump_table() {
+        let map = parse(INPUT);
+        let start_position = map.guard().position;
+        let table = JumpTable::new(&map.obstacles);
+        assert_eq!(
+            table.stop(start_position, Direction::Top, (9, 9)),
+            Some((4, 1))
+        );
+        assert_eq!(table.stop((4, 1), Direction::Right, (9, 9)), Some((8, 1)));
+        assert_eq!(table.stop((4, 1), Direction::Left, (9, 9)), None);
+        // New obstacles ahead, behind and beyond the next one
+        assert_eq!(
+            table.stop(start_position, Direction::Top, (4, 3)),
+            Some((4, 4))
+        );
+        assert_eq!(
+            table.stop(start_position, Direction::Top, (4, 8)),
+            Some((4, 1))
+        );
+        assert_eq!(table.stop((4, 1), Direction::Left, (2, 1)), Some((3, 1)));
+        assert_eq!(table.stop((4, 1), Direction::Right, (9, 1)), Some((8, 1)));
+    }
+
+    #[test]
+    fn day6_jumping_matches_stepping() {
+        let mut rng = XorShift::new(0x2545f4914f6cdd1d);
+
+        for _ in 0..200 {
+            let (width, height) = (2 + rng.below(15) as usize, 2 + rng.below(15) as usize);
+            let start = rng.below((width * height) as u64) as usize;
+            let input: String = (0..height)
+                .map(|y| {
+                    (0..width)
+                        .map(|x| match (y * width + x == start, rng.below(6)) {
+                            (true, glyph) => ['^', '>', 'v', '<'][glyph as usize % 4],
+                            (false, 0) => '#',
+                            _ => '.',
+                        })
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            let map = parse(&input);
+            let mut visited = map.obstacles.visited_set(4);
+            if !can_escape(&mut visited, &map.obstacles, map.guard()) {
+                // The guard has to leave for the walk to be finite
+                continue;
+            }
+            assert_eq!(part_two(&input), part_two_stepping(&input), "{input}");
+            assert_eq!(part_two(&input), part_two_parallel(&input), "{input}");
+        }
+    }
+
+    #[test]
+    fn day6_orientations() {
+        let right = INPUT.replace('^', ">");
+        assert_eq!(parse(&right).guard().direction, Direction::Right);
+        assert_eq!(part_one(&right), 6);
+        assert_eq!(part_one(&INPUT.replace('^', "v")), 4);
+        // Starting on the edge, facing out
+        assert_eq!(part_one("#..\n<.."), 1);
+        assert_eq!(part_one("#..\n.>."), 2);
+    }
+
+    #[test]
+    fn day6_guards() {
+        let patrols = patrol_together(&parse(INPUT));
+        assert_eq!(
+            patrols,
+            Patrols {
+                visited: vec![41],
+                exited: vec![true],
+                collisions: 0
+            }
+        );
+
+        // One guard loops around the obstacles, the other two walk into each other
+        let input = ".#...\n.^.#.\n#....\n..#..\n>...<";
+        let patrols = patrol_together(&parse(input));
+        assert_eq!(
+            patrols,
+            Patrols {
+                visited: vec![4, 5, 5],
+                exited: vec![false, true, true],
+                collisions: 1
+            }
+        );
+        assert_eq!(
+            guards(input),
+            "guard 0 (^ at 1,1): 4 cells, loops
+guard 1 (> at 0,4): 5 cells, exited
+guard 2 (< at 4,4): 5 cells, exited
+collisions: 1
+"
+        );
+    }
+
+    #[test]
+    fn day6_guards_through_loop() {
+        // The first guard loops around the four obstacles in 16 ticks, the second one walks up
+        // through the loop
+        let map = |height: usize, start: usize| {
+            let mut lines = vec!["........"; height];
+            lines[0] = "..#.....";
+            lines[1] = "...>..#.";
+            lines[4] = ".#......";
+            lines[5] = ".....#..";
+            lines[start] = "...^....";
+            parse(&lines.join("\n"))
+        };
+
+        // Never where the first guard is at the same time
+        assert_eq!(
+            patrol_together(&map(26, 25)),
+            Patrols {
+                visited: vec![12, 26],
+                exited: vec![false, true],
+                collisions: 0
+            }
+        );
+        // Meets the first guard on its second lap
+        assert_eq!(
+            patrol_together(&map(30, 29)),
+            Patrols {
+                visited: vec![12, 30],
+                exited: vec![false, true],
+                collisions: 1
+            }
+        );
+    }
+
+    #[test]
+    fn day6_replay() {
+        let with_obstacle = INPUT
+            .lines()
+            .enumerate()
+            .map(|(y, line)| match y {
+                6 => ".#.#^.....",
+                _ => line,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(
+            replay(&with_obstacle),
+            "....#.....
+....+---+#
+....|...|.
+..#.|...|.
+....|..#|.
+....|...|.
+.#.#^---+.
+........#.
+#.........
+......#...
+"
+        );
+    }
+
+    fn simulate(input: &str) -> Vec<((i32, i32), Direction, Event)> {
+        let map = parse(input);
+        let mut states = map.obstacles.visited_set(4);
+        GuardSim::new(&map.obstacles, &mut states, map.guard()).collect()
+    }
+
+    #[test]
+    fn day6_guard_sim() {
+        assert_eq!(
+            simulate("#.\n^."),
+            vec![
+                ((0, 1), Direction::Right, Event::Turned),
+                ((1, 1), Direction::Right, Event::Moved),
+                ((1, 1), Direction::Right, Event::Exited),
+            ]
+        );
+        let looping = simulate(".#..\n.^.#\n#...\n..#.");
+        assert_eq!(looping.len(), 8);
+        assert_eq!(looping[7], ((1, 1), Direction::Top, Event::Looped));
+        assert_eq!(part_one(".#..\n.^.#\n#...\n..#."), 4);
+    }
+
+    #[test]
+    fn day6_guard_on_edge() {
+        // Facing out of the map, from every side
+        for input in [
+            "..^..\n.....",
+            "....>\n.....",
+            ".....\n..v..",
+            ".....\n<....",
+        ] {
+            let map = parse(input);
+            let guard = map.guard();
+            assert_eq!(
+                simulate(input),
+                vec![(guard.position, guard.direction, Event::Exited)],
+                "{input}"
+            );
+            assert_eq!(part_one(input), 1);
+            assert_eq!(part_two(input), 0);
+            assert_eq!(part_two_stepping(input), 0);
+        }
+
+        // Walking along the edge, until the corner
+        assert_eq!(part_one("^....\n.....\n....."), 1);
+        assert_eq!(part_one(".....\n.....\n>...."), 5);
+        assert_eq!(part_one("#....\n.....\n^...."), 6);
     }
 }
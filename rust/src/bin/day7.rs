@@ -1,3 +1,5 @@
+use std::{fmt::Write, ops::ControlFlow};
+
 use aoc24::aoc;
 use nom::{multi::separated_list1, IResult};
 
@@ -6,40 +8,291 @@ struct Equation {
     parts: Vec<i64>,
 }
 
-fn concat(a: i64, b: i64) -> i64 {
-    let tenx = 10i64.pow(b.ilog10() + 1);
-    a * tenx + b
+fn concat(a: i64, b: i64) -> Option<i64> {
+    if b < 0 {
+        return None;
+    }
+    let tenx = 10i64.checked_pow(b.checked_ilog10().unwrap_or(0) + 1)?;
+    a.checked_mul(tenx)?.checked_add(b)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Add,
+    Mul,
+    Concat,
+    Sub,
+    /// Integer division, rounding towards zero
+    Div,
+    Pow,
+    Xor,
+}
+
+impl Operator {
+    /// `None` when the result overflows or is undefined.
+    fn apply(self, a: i64, b: i64) -> Option<i64> {
+        match self {
+            Operator::Add => a.checked_add(b),
+            Operator::Mul => a.checked_mul(b),
+            Operator::Concat => concat(a, b),
+            Operator::Sub => a.checked_sub(b),
+            Operator::Div => a.checked_div(b),
+            Operator::Pow => a.checked_pow(u32::try_from(b).ok()?),
+            Operator::Xor => Some(a ^ b),
+        }
+    }
+
+    /// Whether the result is never below `a`, for operands of at least 1.
+    fn is_monotonic(self) -> bool {
+        match self {
+            Operator::Add | Operator::Mul | Operator::Concat | Operator::Pow => true,
+            Operator::Sub | Operator::Div | Operator::Xor => false,
+        }
+    }
+
+    /// The `a` such that `apply(a, b) == result`, if any. Always `None` for operators without
+    /// a single such `a` (division truncates, powers have several roots), which the reverse
+    /// solver can't handle.
+    fn unapply(self, result: i64, b: i64) -> Option<i64> {
+        match self {
+            Operator::Add => result.checked_sub(b),
+            Operator::Mul => (b != 0 && result % b == 0).then(|| result / b),
+            Operator::Concat => {
+                let tenx = 10i64.checked_pow(b.checked_ilog10()? + 1)?;
+                (result >= 0 && result % tenx == b).then_some(result / tenx)
+            }
+            Operator::Sub => result.checked_add(b),
+            Operator::Xor => Some(result ^ b),
+            Operator::Div | Operator::Pow => None,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Operator::Add => "+",
+            Operator::Mul => "*",
+            Operator::Concat => "||",
+            Operator::Sub => "-",
+            Operator::Div => "/",
+            Operator::Pow => "**",
+            Operator::Xor => "^",
+        }
+    }
+
+    /// Higher binds tighter, when not evaluating from left to right.
+    fn precedence(self) -> u8 {
+        match self {
+            Operator::Concat => 4,
+            Operator::Pow => 3,
+            Operator::Mul | Operator::Div => 2,
+            Operator::Add | Operator::Sub => 1,
+            Operator::Xor => 0,
+        }
+    }
+}
+
+/// Operators that can be put between parts, and how they are evaluated: strictly from left to
+/// right like the puzzle, or by [`Operator::precedence`] (all of them left associative).
+struct OperatorSet {
+    operators: &'static [Operator],
+    precedence: bool,
+}
+
+const PART_ONE: &OperatorSet = &OperatorSet {
+    operators: &[Operator::Add, Operator::Mul],
+    precedence: false,
+};
+
+const PART_TWO: &OperatorSet = &OperatorSet {
+    operators: &[Operator::Add, Operator::Mul, Operator::Concat],
+    precedence: false,
+};
+
+// Every operator, from left to right
+const EXTENDED: &OperatorSet = &OperatorSet {
+    operators: &[
+        Operator::Add,
+        Operator::Mul,
+        Operator::Concat,
+        Operator::Sub,
+        Operator::Div,
+        Operator::Pow,
+        Operator::Xor,
+    ],
+    precedence: false,
+};
+
+// Usual arithmetic, multiplications first
+const ARITHMETIC: &OperatorSet = &OperatorSet {
+    operators: &[Operator::Add, Operator::Sub, Operator::Mul, Operator::Div],
+    precedence: true,
+};
+
+// `prune`: no operator can bring `curr` back down once over `result`
+// Calls `on_solution` with the operators of every solution, until it breaks
+fn search(
+    result: i64,
+    curr: i64,
+    parts: &[i64],
+    set: &OperatorSet,
+    prune: bool,
+    operators: &mut Vec<Operator>,
+    on_solution: &mut dyn FnMut(&[Operator]) -> ControlFlow<()>,
+) -> ControlFlow<()> {
+    let Some((value, next)) = parts.split_first() else {
+        if curr == result {
+            return on_solution(operators);
+        }
+        return ControlFlow::Continue(());
+    };
+    for op in set.operators {
+        let Some(applied) = op.apply(curr, *value) else {
+            continue;
+        };
+        if prune && applied > result {
+            continue;
+        }
+        operators.push(*op);
+        search(result, applied, next, set, prune, operators, on_solution)?;
+        operators.pop();
+    }
+    ControlFlow::Continue(())
+}
+
+// Works back from the result: the last part has to be undone into a value the parts before can
+// reach. Only for monotonic operators with inverses and parts of at least 1, so every value
+// along the way is at least 1.
+fn is_solvable_reverse(result: i64, parts: &[i64], set: &OperatorSet) -> bool {
+    let (value, rest) = parts.split_last().expect("At least one part");
+    if rest.is_empty() {
+        return result == *value;
+    }
+    set.operators
+        .iter()
+        .filter_map(|op| op.unapply(result, *value))
+        .any(|undone| undone >= 1 && is_solvable_reverse(undone, rest, set))
 }
 
-fn is_solvable(result: i64, curr: i64, parts: &[i64], can_concat: bool) -> bool {
-    match parts.len() {
-        0 => unreachable!(),
-        1 => {
-            curr + parts[0] == result
-                || curr * parts[0] == result
-                || can_concat && (concat(curr, parts[0]) == result)
+// Evaluates `parts` with `operators` in between, by precedence
+fn evaluate(parts: &[i64], operators: &[Operator]) -> Option<i64> {
+    let mut values = vec![parts[0]];
+    let mut pending: Vec<Operator> = Vec::new();
+    let reduce = |values: &mut Vec<i64>, op: Operator| {
+        let b = values.pop().expect("Right operand");
+        let a = values.pop().expect("Left operand");
+        values.push(op.apply(a, b)?);
+        Some(())
+    };
+    for (op, value) in operators.iter().zip(&parts[1..]) {
+        while let Some(top) = pending.last() {
+            if top.precedence() < op.precedence() {
+                break;
+            }
+            reduce(&mut values, *top)?;
+            pending.pop();
         }
-        _ => {
-            let value = &parts[0];
-            let next = &parts[1..];
-
-            let added = curr + value;
-            let multiplied = curr * value;
-            let concatted = concat(curr, *value);
-
-            (added <= result && is_solvable(result, added, next, can_concat))
-                || (multiplied <= result && is_solvable(result, multiplied, next, can_concat))
-                || (can_concat
-                    && concatted.ilog10() <= result.ilog10()
-                    && is_solvable(result, concatted, next, can_concat))
+        pending.push(*op);
+        values.push(*value);
+    }
+    while let Some(op) = pending.pop() {
+        reduce(&mut values, op)?;
+    }
+    values.pop()
+}
+
+// Tries every assignment of operators, evaluated by precedence
+fn search_by_precedence(
+    result: i64,
+    parts: &[i64],
+    set: &OperatorSet,
+    operators: &mut Vec<Operator>,
+    on_solution: &mut dyn FnMut(&[Operator]) -> ControlFlow<()>,
+) -> ControlFlow<()> {
+    if operators.len() == parts.len() - 1 {
+        if evaluate(parts, operators) == Some(result) {
+            return on_solution(operators);
         }
+        return ControlFlow::Continue(());
+    }
+    for op in set.operators {
+        operators.push(*op);
+        search_by_precedence(result, parts, set, operators, on_solution)?;
+        operators.pop();
     }
+    ControlFlow::Continue(())
 }
 
 impl Equation {
-    pub fn is_solvable(&self, can_concat: bool) -> bool {
+    fn search(
+        &self,
+        set: &OperatorSet,
+        on_solution: &mut dyn FnMut(&[Operator]) -> ControlFlow<()>,
+    ) {
+        let Equation { result, parts } = self;
+        let mut operators = Vec::with_capacity(parts.len() - 1);
+        if set.precedence {
+            let _ = search_by_precedence(*result, parts, set, &mut operators, on_solution);
+            return;
+        }
+        let prune =
+            set.operators.iter().all(|op| op.is_monotonic()) && parts.iter().all(|p| *p >= 1);
+        let _ = search(
+            *result,
+            parts[0],
+            &parts[1..],
+            set,
+            prune,
+            &mut operators,
+            on_solution,
+        );
+    }
+
+    /// Operators between the parts for one of the solutions.
+    pub fn solution(&self, set: &OperatorSet) -> Option<Vec<Operator>> {
+        let mut solution = None;
+        self.search(set, &mut |operators| {
+            solution = Some(operators.to_vec());
+            ControlFlow::Break(())
+        });
+        solution
+    }
+
+    /// Every solution, in the order of `set.operators`.
+    pub fn solutions(&self, set: &OperatorSet) -> Vec<Vec<Operator>> {
+        let mut solutions = Vec::new();
+        self.search(set, &mut |operators| {
+            solutions.push(operators.to_vec());
+            ControlFlow::Continue(())
+        });
+        solutions
+    }
+
+    pub fn is_solvable(&self, set: &OperatorSet) -> bool {
+        self.solution(set).is_some()
+    }
+
+    /// The equation with `operators` between the parts, e.g. `3267 = 81 * 40 + 27`.
+    pub fn format(&self, operators: &[Operator]) -> String {
+        let mut text = format!("{} = {}", self.result, self.parts[0]);
+        for (op, part) in operators.iter().zip(&self.parts[1..]) {
+            write!(text, " {} {part}", op.symbol()).expect("String write");
+        }
+        text
+    }
+
+    /// Same as [`Equation::is_solvable`], starting from the result when possible.
+    pub fn is_solvable_reverse(&self, set: &OperatorSet) -> bool {
         let Equation { result, parts } = self;
-        is_solvable(*result, parts[0], &parts[1..], can_concat)
+        let reversible = !set.precedence
+            && set
+                .operators
+                .iter()
+                .all(|op| matches!(op, Operator::Add | Operator::Mul | Operator::Concat))
+            && parts.iter().all(|p| *p >= 1);
+        if !reversible {
+            return self.is_solvable(set);
+        }
+        is_solvable_reverse(*result, parts, set)
     }
 }
 
@@ -60,24 +313,54 @@ fn parse(input: &str) -> Vec<Equation> {
         .collect()
 }
 
-fn part_one(input: &str) -> i64 {
+fn calibration(input: &str, set: &OperatorSet, solver: fn(&Equation, &OperatorSet) -> bool) -> i64 {
     parse(input)
         .into_iter()
-        .filter_map(|eq| eq.is_solvable(false).then_some(eq.result))
+        .filter_map(|eq| solver(&eq, set).then_some(eq.result))
         .sum()
 }
 
+fn part_one(input: &str) -> i64 {
+    calibration(input, PART_ONE, Equation::is_solvable_reverse)
+}
+
 fn part_two(input: &str) -> i64 {
-    parse(input)
-        .into_iter()
-        .filter_map(|eq| eq.is_solvable(true).then_some(eq.result))
-        .sum()
+    calibration(input, PART_TWO, Equation::is_solvable_reverse)
 }
 
-aoc!(part_one, part_two);
+fn part_one_forward(input: &str) -> i64 {
+    calibration(input, PART_ONE, Equation::is_solvable)
+}
+
+fn part_two_forward(input: &str) -> i64 {
+    calibration(input, PART_TWO, Equation::is_solvable)
+}
+
+fn extended(input: &str) -> i64 {
+    calibration(input, EXTENDED, Equation::is_solvable_reverse)
+}
+
+fn arithmetic(input: &str) -> i64 {
+    calibration(input, ARITHMETIC, Equation::is_solvable_reverse)
+}
+
+// Every solution of every equation, with the operators of part two
+fn solutions(input: &str) -> String {
+    let mut text = String::new();
+    for equation in parse(input) {
+        for operators in equation.solutions(PART_TWO) {
+            writeln!(text, "{}", equation.format(&operators)).expect("String write");
+        }
+    }
+    text
+}
+
+aoc!(part_one, part_two, part_one_forward, part_two_forward; extended, arithmetic, solutions);
 
 #[cfg(test)]
 pub mod tests {
+    use aoc24::rng::XorShift;
+
     use super::*;
 
     const INPUT: &str = "190: 10 19
@@ -94,10 +377,163 @@ pub mod tests {
     fn day7() {
         assert_eq!(part_one(INPUT), 3749);
         assert_eq!(part_two(INPUT), 11387);
+        assert_eq!(part_one_forward(INPUT), 3749);
+        assert_eq!(part_two_forward(INPUT), 11387);
     }
 
     #[test]
     fn should_concat() {
-        assert_eq!(concat(12, 123), 12123);
+        assert_eq!(concat(12, 123), Some(12123));
+        assert_eq!(concat(12, 0), Some(120));
+        assert_eq!(concat(i64::MAX / 10, 123), None);
+    }
+
+    fn solvable(equation: &str, set: &OperatorSet) -> bool {
+        parse(equation)[0].is_solvable(set)
+    }
+
+    #[test]
+    fn day7_operator_sets() {
+        use Operator::*;
+        let all = EXTENDED;
+        assert!(!solvable("5: 10 5", PART_TWO));
+        assert!(solvable("5: 10 5", all));
+        assert!(solvable("2: 10 5", all));
+        assert!(solvable("1000: 10 3", all));
+        assert!(solvable("6: 3 5", all));
+        // 10 - 15 + 6
+        assert!(solvable("1: 10 15 6", all));
+        assert!(solvable(
+            "1: 10 0",
+            &OperatorSet {
+                operators: &[Pow],
+                precedence: false,
+            }
+        ));
+
+        // Results out of range are not solutions
+        assert!(!solvable("0: 9223372036854775807 2 0", PART_ONE));
+        assert!(solvable(
+            "0: 9223372036854775807 2 0",
+            &OperatorSet {
+                operators: &[Add, Mul, Sub],
+                precedence: false,
+            }
+        ));
+    }
+
+    #[test]
+    fn day7_precedence() {
+        use Operator::*;
+        // 2 + 3 * 4
+        assert!(solvable("14: 2 3 4", ARITHMETIC));
+        assert!(!solvable("14: 2 3 4", PART_ONE));
+        assert_eq!(evaluate(&[2, 3, 4, 5], &[Mul, Add, Mul]), Some(26));
+        assert_eq!(evaluate(&[20, 2, 5], &[Sub, Sub]), Some(13));
+        assert_eq!(
+            evaluate(&[2, 3, 2], &[Pow, Concat]),
+            Some(2usize.pow(32) as i64)
+        );
+        assert_eq!(evaluate(&[1, 2], &[Div]), Some(0));
+        assert_eq!(evaluate(&[1, 0], &[Div]), None);
+    }
+
+    #[test]
+    fn day7_reverse_matches_forward() {
+        let mut rng = XorShift::new(0xd1b54a32d192ed03);
+
+        for _ in 0..3000 {
+            let len = 1 + rng.below(7) as usize;
+            let parts: Vec<i64> = (0..len)
+                .map(|_| match rng.below(10) {
+                    // Close to overflowing
+                    0 => i64::MAX - rng.below(1000) as i64,
+                    1 => 1 << (40 + rng.below(22)),
+                    _ => 1 + rng.below(20) as i64,
+                })
+                .collect();
+            // Reachable results most of the time, to exercise both answers
+            let ops = [Operator::Add, Operator::Mul, Operator::Concat];
+            let result = parts[1..]
+                .iter()
+                .try_fold(parts[0], |acc, p| ops[rng.below(3) as usize].apply(acc, *p))
+                .filter(|_| rng.below(4) != 0)
+                .unwrap_or_else(|| rng.below(1 << 40) as i64);
+            let equation = Equation { result, parts };
+            for set in [PART_ONE, PART_TWO] {
+                assert_eq!(
+                    equation.is_solvable(set),
+                    equation.is_solvable_reverse(set),
+                    "{result}: {:?}",
+                    equation.parts
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn day7_overflow() {
+        assert!(!solvable(
+            "9223372036854775807: 4611686018427387904 2",
+            PART_TWO
+        ));
+        assert!(
+            !parse("9223372036854775807: 4611686018427387904 2")[0].is_solvable_reverse(PART_TWO)
+        );
+        assert!(
+            parse("9223372036854775806: 4611686018427387903 2")[0].is_solvable_reverse(PART_ONE)
+        );
+        assert!(parse("9223372036854775807: 922337203685477580 7")[0].is_solvable_reverse(PART_TWO));
+        assert!(
+            !parse("9223372036854775807: 922337203685477580 7")[0].is_solvable_reverse(PART_ONE)
+        );
+    }
+
+    #[test]
+    fn day7_reverse_any_set() {
+        assert_eq!(Operator::Div.unapply(8, 3), None);
+        assert_eq!(Operator::Pow.unapply(8, 3), None);
+        // 2 ** 3, found by falling back to the forward solver
+        let equation = Equation {
+            result: 8,
+            parts: vec![2, 3],
+        };
+        for set in [EXTENDED, ARITHMETIC] {
+            assert_eq!(equation.is_solvable_reverse(set), equation.is_solvable(set));
+        }
+        assert!(equation.is_solvable_reverse(EXTENDED));
+    }
+
+    #[test]
+    fn day7_solutions() {
+        use Operator::*;
+        let equations = parse(INPUT);
+        assert_eq!(equations[0].solution(PART_ONE), Some(vec![Mul]));
+        assert_eq!(
+            equations[1].solutions(PART_ONE),
+            vec![vec![Add, Mul], vec![Mul, Add]]
+        );
+        assert_eq!(equations[1].format(&[Mul, Add]), "3267 = 81 * 40 + 27");
+        assert_eq!(equations[2].solution(PART_TWO), None);
+        assert_eq!(
+            equations[4].solutions(PART_TWO),
+            vec![vec![Mul, Concat, Mul]]
+        );
+        assert_eq!(
+            parse("14: 2 3 4")[0].solutions(ARITHMETIC),
+            vec![vec![Add, Mul]]
+        );
+
+        assert_eq!(
+            solutions(INPUT),
+            "190 = 10 * 19
+3267 = 81 + 40 * 27
+3267 = 81 * 40 + 27
+156 = 15 || 6
+7290 = 6 * 8 || 6 * 15
+192 = 17 || 8 + 14
+292 = 11 + 6 * 16 + 20
+"
+        );
     }
 }
@@ -1,107 +1,268 @@
-use aoc24::aoc;
+use std::fmt::Write;
+
+use aoc24::{
+    aoc,
+    geometry::{BoundingBox, Point},
+    grid::SparseGrid,
+    viz::Frame,
+};
 use fxhash::{FxHashMap, FxHashSet};
+use itertools::Itertools;
 
 pub struct Map {
-    antennas: FxHashMap<char, FxHashSet<(i32, i32)>>,
-    limit: (i32, i32),
+    antennas: FxHashMap<char, FxHashSet<Point<2>>>,
+    bounds: BoundingBox<2>,
 }
 
 fn parse(input: &str) -> Map {
-    let mut antennas: FxHashMap<char, FxHashSet<(i32, i32)>> = FxHashMap::default();
-    let mut limit = (0, 0);
-    for (y, line) in input.lines().enumerate() {
-        for (x, c) in line.chars().enumerate() {
-            limit.0 = limit.0.max(x as i32);
-            match c {
-                '.' => (),
-                c => {
-                    let group = antennas.entry(c).or_default();
-                    group.insert((x as i32, y as i32));
-                }
-            }
-        }
-        limit.1 = y as i32;
+    let grid = SparseGrid::parse(input, |_, c| (c != '.').then_some(c));
+    let mut antennas: FxHashMap<char, FxHashSet<Point<2>>> = FxHashMap::default();
+    for (pos, c) in grid.iter() {
+        let group = antennas.entry(*c).or_default();
+        group.insert(pos);
     }
-    Map { antennas, limit }
+    let bounds = grid.bounds().expect("non empty map");
+    Map { antennas, bounds }
 }
 
-struct AntinodeIterator {
-    k: i32,
-    a: (i32, i32),
-    b: (i32, i32),
-    limit: (i32, i32),
+/// Where a pair of antennas of the same frequency produces antinodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Resonance {
+    /// Twice as far from one antenna as from the other, for part one
+    Pair,
+    /// Any multiple of the distance between the antennas, for part two
+    Harmonics,
+    /// Every cell on the line through the antennas, which [`Resonance::Harmonics`] skips when
+    /// the distance has a common factor
+    Line,
 }
 
-impl AntinodeIterator {
-    pub fn new(limit: (i32, i32), a: (i32, i32), b: (i32, i32)) -> Self {
-        AntinodeIterator { limit, k: 0, a, b }
+fn gcd(a: i32, b: i32) -> i32 {
+    match b {
+        0 => a.abs(),
+        b => gcd(b, a % b),
     }
 }
 
-impl Iterator for AntinodeIterator {
-    type Item = (i32, i32);
+// Rounds towards negative infinity, for any sign of `d`
+fn div_floor(n: i32, d: i32) -> i32 {
+    if d < 0 {
+        return div_floor(-n, -d);
+    }
+    n.div_euclid(d)
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let Self { k, a, b, limit } = self;
-        let inbounds = |x: i32, y: i32| x >= 0 && x <= limit.0 && y >= 0 && y <= limit.1;
+fn div_ceil(n: i32, d: i32) -> i32 {
+    -div_floor(-n, d)
+}
 
-        let (x, y) = ((*k + 1) * a.0 - *k * b.0, (*k + 1) * a.1 - *k * b.1);
-        self.k += 1;
+/// Points `a + step * k` within the bounds, computing the range of `k` instead of walking out
+/// of the bounds. `step` can't be zero.
+fn clipped_line(
+    bounds: BoundingBox<2>,
+    a: Point<2>,
+    step: Point<2>,
+) -> impl Iterator<Item = Point<2>> {
+    let mut k_min = i32::MIN;
+    let mut k_max = i32::MAX;
+    for axis in 0..2 {
+        let (origin, d) = (a.0[axis], step.0[axis]);
+        let (min, max) = (bounds.min.0[axis] - origin, bounds.max.0[axis] - origin);
+        match d.signum() {
+            0 if min > 0 || max < 0 => (k_min, k_max) = (1, 0),
+            0 => {}
+            1 => {
+                k_min = k_min.max(div_ceil(min, d));
+                k_max = k_max.min(div_floor(max, d));
+            }
+            _ => {
+                k_min = k_min.max(div_ceil(max, d));
+                k_max = k_max.min(div_floor(min, d));
+            }
+        }
+    }
+    (k_min..=k_max).map(move |k| a + step * k)
+}
 
-        inbounds(x, y).then_some((x, y))
+fn antinodes(
+    bounds: BoundingBox<2>,
+    a: Point<2>,
+    b: Point<2>,
+    resonance: Resonance,
+) -> Vec<Point<2>> {
+    let delta = a - b;
+    match resonance {
+        Resonance::Pair => [a + delta, b - delta]
+            .into_iter()
+            .filter(|p| bounds.contains(*p))
+            .collect(),
+        Resonance::Harmonics => clipped_line(bounds, a, delta).collect(),
+        Resonance::Line => {
+            let step = Point(delta.0.map(|c| c / gcd(delta.0[0], delta.0[1])));
+            clipped_line(bounds, a, step).collect()
+        }
     }
 }
 
-fn part_one(input: &str) -> usize {
-    let Map { antennas, limit } = parse(input);
+// Antinodes of every pair of antennas of a frequency
+fn frequency_antinodes(
+    bounds: BoundingBox<2>,
+    positions: &FxHashSet<Point<2>>,
+    resonance: Resonance,
+) -> FxHashSet<Point<2>> {
+    positions
+        .iter()
+        .tuple_combinations()
+        .flat_map(|(a, b)| antinodes(bounds, *a, *b, resonance))
+        .collect()
+}
 
+fn count_antinodes(input: &str, resonance: Resonance) -> usize {
+    let Map { antennas, bounds } = parse(input);
     antennas
-        .into_iter()
-        .flat_map(|(_, positions)| {
-            let mut antinodes = FxHashSet::<(i32, i32)>::default();
-            for a in &positions {
-                for b in &positions {
-                    if a == b {
-                        continue;
-                    }
-                    let antinodes_a_to_b = AntinodeIterator::new(limit, *a, *b).skip(1).take(1);
-                    let antinodes_b_to_a = AntinodeIterator::new(limit, *b, *a).skip(1).take(1);
-                    antinodes.extend(antinodes_a_to_b.chain(antinodes_b_to_a))
-                }
-            }
-            antinodes
-        })
+        .values()
+        .flat_map(|positions| frequency_antinodes(bounds, positions, resonance))
         .collect::<FxHashSet<_>>()
         .len()
 }
 
+fn part_one(input: &str) -> usize {
+    count_antinodes(input, Resonance::Pair)
+}
+
 fn part_two(input: &str) -> usize {
-    let Map { antennas, limit } = parse(input);
+    count_antinodes(input, Resonance::Harmonics)
+}
 
-    antennas
+fn part_two_line(input: &str) -> usize {
+    count_antinodes(input, Resonance::Line)
+}
+
+// Frequencies in order, with their antinodes
+fn antinodes_by_frequency(map: &Map, resonance: Resonance) -> Vec<(char, FxHashSet<Point<2>>)> {
+    map.antennas
+        .iter()
+        .map(|(frequency, positions)| {
+            (
+                *frequency,
+                frequency_antinodes(map.bounds, positions, resonance),
+            )
+        })
+        .sorted_by_key(|(frequency, _)| *frequency)
+        .collect()
+}
+
+// Antinodes of each frequency, then the ones shared by two frequencies
+fn frequencies(input: &str) -> String {
+    let map = parse(input);
+    let by_frequency = antinodes_by_frequency(&map, Resonance::Harmonics);
+    let mut text = String::new();
+    for (frequency, antinodes) in &by_frequency {
+        writeln!(
+            text,
+            "{frequency}: {} antennas, {} antinodes",
+            map.antennas[frequency].len(),
+            antinodes.len()
+        )
+        .expect("String write");
+    }
+    for ((f, a), (g, b)) in by_frequency.iter().tuple_combinations() {
+        let shared = a.intersection(b).count();
+        if shared > 0 {
+            writeln!(text, "{f} & {g}: {shared} shared antinodes").expect("String write");
+        }
+    }
+    text
+}
+
+/// Number of antenna pairs (of the same frequency) with an antinode on each cell.
+fn heat(map: &Map, resonance: Resonance) -> FxHashMap<Point<2>, usize> {
+    let mut heat: FxHashMap<Point<2>, usize> = FxHashMap::default();
+    for positions in map.antennas.values() {
+        for (a, b) in positions.iter().tuple_combinations() {
+            for antinode in antinodes(map.bounds, *a, *b, resonance) {
+                *heat.entry(antinode).or_default() += 1;
+            }
+        }
+    }
+    heat
+}
+
+// The map with the heat of each cell, `+` past 9
+fn heatmap(input: &str) -> String {
+    let map = parse(input);
+    let bounds = map.bounds;
+    let mut frame = Frame::new(bounds.width(), bounds.height());
+    for (pos, count) in heat(&map, Resonance::Harmonics) {
+        let glyph = char::from_digit(count as u32, 10).unwrap_or('+');
+        let (x, y) = (pos - bounds.min).into();
+        frame.set((x, y), glyph);
+    }
+    frame.to_text()
+}
+
+/// Where to put one more antenna, and of which frequency, for the most unique antinodes.
+#[derive(Debug, PartialEq, Eq)]
+struct Placement {
+    position: Point<2>,
+    frequency: char,
+    antinodes: usize,
+}
+
+// An antenna only adds pairs, so the new antinodes are the ones of its pairs not already there.
+// Ties go to the first frequency, then the first cell in reading order.
+fn best_placement(map: &Map, resonance: Resonance) -> Option<Placement> {
+    let all: FxHashSet<Point<2>> = antinodes_by_frequency(map, resonance)
         .into_iter()
-        .flat_map(|(_, positions)| {
-            let mut antinodes = FxHashSet::<(i32, i32)>::default();
-            for a in &positions {
-                for b in &positions {
-                    if a == b {
-                        continue;
-                    }
-                    let antinodes_a_to_b = AntinodeIterator::new(limit, *a, *b);
-                    let antinodes_b_to_a = AntinodeIterator::new(limit, *b, *a);
-                    antinodes.extend(antinodes_a_to_b.chain(antinodes_b_to_a))
-                }
+        .flat_map(|(_, antinodes)| antinodes)
+        .collect();
+    let occupied: FxHashSet<Point<2>> = map.antennas.values().flatten().copied().collect();
+    let mut cells: Vec<Point<2>> = map
+        .bounds
+        .points()
+        .filter(|p| !occupied.contains(p))
+        .collect();
+    cells.sort_by_key(|p| (p.0[1], p.0[0]));
+
+    let mut best: Option<Placement> = None;
+    for frequency in map.antennas.keys().sorted() {
+        let positions = &map.antennas[frequency];
+        for position in &cells {
+            let added: FxHashSet<Point<2>> = positions
+                .iter()
+                .flat_map(|a| antinodes(map.bounds, *position, *a, resonance))
+                .filter(|p| !all.contains(p))
+                .collect();
+            let antinodes = all.len() + added.len();
+            if best.as_ref().is_none_or(|b| antinodes > b.antinodes) {
+                best = Some(Placement {
+                    position: *position,
+                    frequency: *frequency,
+                    antinodes,
+                });
             }
-            antinodes
-        })
-        .collect::<FxHashSet<_>>()
-        .len()
+        }
+    }
+    best
+}
+
+fn placement(input: &str) -> String {
+    match best_placement(&parse(input), Resonance::Harmonics) {
+        Some(Placement {
+            position: Point([x, y]),
+            frequency,
+            antinodes,
+        }) => format!("{frequency} at {x},{y}: {antinodes} antinodes"),
+        None => "no room for another antenna".to_string(),
+    }
 }
 
-aoc!(part_one, part_two);
+aoc!(part_one, part_two, part_two_line; frequencies, heatmap, placement);
 
 #[cfg(test)]
 pub mod tests {
+    use aoc24::rng::XorShift;
+
     use super::*;
 
     const INPUT: &str = "............
@@ -121,5 +282,67 @@ pub mod tests {
     fn day8() {
         assert_eq!(part_one(INPUT), 14);
         assert_eq!(part_two(INPUT), 34);
+        assert_eq!(part_two_line(INPUT), 34);
+    }
+
+    #[test]
+    fn day8_line() {
+        let input = "A....\n.....\n..A..\n.....\n.....";
+        assert_eq!(part_one(input), 1);
+        assert_eq!(part_two(input), 3);
+        assert_eq!(part_two_line(input), 5);
+
+        let input = "A....\n.....\n.A...\n.....\n.....";
+        assert_eq!(part_two(input), 3);
+        assert_eq!(part_two_line(input), 3);
+    }
+
+    #[test]
+    fn day8_clipped_line_matches_walking() {
+        let mut rng = XorShift::new(0x853c49e6748fea9b);
+
+        for _ in 0..2000 {
+            let mut bounds = BoundingBox::new([rng.below(10) as i32 - 5, rng.below(10) as i32 - 5]);
+            bounds.include([rng.below(10) as i32 - 5, rng.below(10) as i32 - 5]);
+            let a = Point([rng.below(30) as i32 - 15, rng.below(30) as i32 - 15]);
+            let step = Point([rng.below(9) as i32 - 4, rng.below(9) as i32 - 4]);
+            if step == Point::ORIGIN {
+                continue;
+            }
+            let mut walked: Vec<Point<2>> = (-40..=40)
+                .map(|k| a + step * k)
+                .filter(|p| bounds.contains(*p))
+                .collect();
+            walked.sort();
+            let mut clipped: Vec<Point<2>> = clipped_line(bounds, a, step).collect();
+            clipped.sort();
+            assert_eq!(clipped, walked, "{bounds:?} {a:?} {step:?}");
+        }
+    }
+
+    #[test]
+    fn day8_analysis() {
+        assert_eq!(
+            frequencies(INPUT),
+            "0: 4 antennas, 21 antinodes\nA: 3 antennas, 16 antinodes\n0 & A: 3 shared antinodes\n"
+        );
+
+        let map = parse(INPUT);
+        let heat = heat(&map, Resonance::Harmonics);
+        assert_eq!(heat.len(), part_two(INPUT));
+        // Every antenna pair has both antennas as antinodes
+        let pairs = 4 * 3 / 2 + 3 * 2 / 2;
+        assert!(heat.values().sum::<usize>() >= 2 * pairs);
+        assert_eq!(heatmap("A.A..").trim(), "1.1.1");
+
+        let best = best_placement(&map, Resonance::Harmonics).expect("empty cells");
+        assert!(best.antinodes > part_two(INPUT));
+        let mut placed = INPUT.lines().map(|l| l.to_string()).collect::<Vec<_>>();
+        let Point([x, y]) = best.position;
+        placed[y as usize].replace_range(x as usize..x as usize + 1, &best.frequency.to_string());
+        assert_eq!(part_two(&placed.join("\n")), best.antinodes);
+
+        assert_eq!(placement("A.A.."), "A at 1,0: 5 antinodes");
+        assert_eq!(placement("AA"), "no room for another antenna");
     }
 }
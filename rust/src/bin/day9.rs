@@ -1,3 +1,5 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
 use aoc24::aoc;
 
 #[derive(Debug)]
@@ -100,7 +102,89 @@ fn part_one(input: &str) -> usize {
     disk.checksum()
 }
 
+/// A file as a run of blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Span {
+    id: usize,
+    start: usize,
+    len: usize,
+}
+
+/// Files and free spaces as spans, free spaces being indexed by size for whole-file moves.
+#[derive(Debug)]
+struct SpanDisk {
+    files: Vec<Span>,
+    /// Start of every free space, by size (1 to 9), smallest first
+    free: [BinaryHeap<Reverse<usize>>; 10],
+}
+
+impl SpanDisk {
+    fn parse(input: &str) -> Self {
+        let mut files = Vec::new();
+        let mut free: [BinaryHeap<Reverse<usize>>; 10] = Default::default();
+        let mut start = 0;
+        for (idx, c) in input.trim().bytes().enumerate() {
+            let len = (c - b'0') as usize;
+            if idx % 2 == 0 {
+                files.push(Span {
+                    id: idx / 2,
+                    start,
+                    len,
+                });
+            } else if len > 0 {
+                free[len].push(Reverse(start));
+            }
+            start += len;
+        }
+        SpanDisk { files, free }
+    }
+
+    /// Start of the leftmost free space of at least `len` blocks before `before`, taken out
+    /// of the free lists. What is left of the space goes back in the list of its new size.
+    fn take_free_space(&mut self, len: usize, before: usize) -> Option<usize> {
+        let (size, Reverse(start)) = (len..self.free.len())
+            .filter_map(|size| self.free[size].peek().map(|start| (size, *start)))
+            .min_by_key(|(_, Reverse(start))| *start)?;
+        if start >= before {
+            return None;
+        }
+        self.free[size].pop();
+        if size > len {
+            self.free[size - len].push(Reverse(start + len));
+        }
+        Some(start)
+    }
+
+    // Each file, from the last one, moves once to the leftmost space that fits it. The space
+    // it leaves is never used: only files further left could, and they only move left.
+    fn compact_files(&mut self) {
+        for idx in (0..self.files.len()).rev() {
+            let Span { start, len, .. } = self.files[idx];
+            if len == 0 {
+                continue;
+            }
+            if let Some(free) = self.take_free_space(len, start) {
+                self.files[idx].start = free;
+            }
+        }
+    }
+
+    fn checksum(&self) -> usize {
+        self.files
+            .iter()
+            .map(|Span { id, start, len }| id * (start * len + len * len.saturating_sub(1) / 2))
+            .sum()
+    }
+}
+
 fn part_two(input: &str) -> usize {
+    let mut disk = SpanDisk::parse(input);
+    disk.compact_files();
+    disk.checksum()
+}
+
+// Moving files block by block
+fn part_two_blocks(input: &str) -> usize {
     let mut disk = parse(input);
     let mut idx = disk.last_file_idx;
     while idx > disk.first_empty_space {
@@ -122,10 +206,12 @@ fn part_two(input: &str) -> usize {
     disk.checksum()
 }
 
-aoc!(part_one, part_two);
+aoc!(part_one, part_two, part_two_blocks);
 
 #[cfg(test)]
 pub mod tests {
+    use aoc24::rng::XorShift;
+
     use super::*;
 
     const INPUT: &str = "2333133121414131402";
@@ -134,5 +220,65 @@ pub mod tests {
     fn day9() {
         assert_eq!(part_one(INPUT), 1928);
         assert_eq!(part_two(INPUT), 2858);
+        assert_eq!(part_two_blocks(INPUT), 2858);
+    }
+
+    #[test]
+    fn day9_spans() {
+        let mut disk = SpanDisk::parse("12345");
+        assert_eq!(disk.take_free_space(3, 15), Some(6));
+        assert_eq!(disk.take_free_space(2, 15), Some(1));
+        assert_eq!(disk.take_free_space(1, 15), Some(9));
+        assert_eq!(disk.take_free_space(1, 15), None);
+
+        let mut disk = SpanDisk::parse("1313165");
+        disk.compact_files();
+        assert_eq!(
+            disk.files,
+            vec![
+                Span {
+                    id: 0,
+                    start: 0,
+                    len: 1
+                },
+                Span {
+                    id: 1,
+                    start: 2,
+                    len: 1
+                },
+                Span {
+                    id: 2,
+                    start: 1,
+                    len: 1
+                },
+                Span {
+                    id: 3,
+                    start: 9,
+                    len: 5
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn day9_spans_match_blocks() {
+        let mut rng = XorShift::new(0xa0761d6478bd642f);
+
+        for _ in 0..500 {
+            let files = 1 + rng.below(40) as usize;
+            let mut input = String::new();
+            for idx in 0..files {
+                input.push(char::from(b'1' + rng.below(9) as u8));
+                if idx + 1 < files {
+                    // At least one free block, the block by block version needs it
+                    let min = if idx == 0 { 1 } else { 0 };
+                    input.push(char::from(b'0' + (min + rng.below(10 - min)) as u8));
+                }
+            }
+            if files == 1 {
+                input.push_str("11");
+            }
+            assert_eq!(part_two(&input), part_two_blocks(&input), "{input}");
+        }
     }
 }
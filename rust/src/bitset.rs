@@ -0,0 +1,84 @@
+/// Set of `(cell index, direction)` states, stored as one bit per state.
+///
+/// Every word of the bitset is stamped with the generation it was last written in, so
+/// [`VisitedSet::clear`] only has to bump the current generation: stale words read as empty.
+#[derive(Debug, Clone)]
+pub struct VisitedSet {
+    words: Vec<u64>,
+    generations: Vec<u32>,
+    generation: u32,
+    directions: usize,
+}
+
+impl VisitedSet {
+    pub fn new(cells: usize, directions: usize) -> Self {
+        let len = (cells * directions).div_ceil(64);
+        VisitedSet {
+            words: vec![0; len],
+            generations: vec![0; len],
+            generation: 1,
+            directions,
+        }
+    }
+
+    fn slot(&self, cell: usize, direction: usize) -> (usize, u64) {
+        debug_assert!(direction < self.directions, "direction out of range");
+        let bit = cell * self.directions + direction;
+        (bit / 64, 1 << (bit % 64))
+    }
+
+    /// Returns `true` if the state was not visited yet.
+    pub fn insert(&mut self, cell: usize, direction: usize) -> bool {
+        let (word, mask) = self.slot(cell, direction);
+        if self.generations[word] != self.generation {
+            self.generations[word] = self.generation;
+            self.words[word] = 0;
+        }
+        let inserted = self.words[word] & mask == 0;
+        self.words[word] |= mask;
+        inserted
+    }
+
+    pub fn contains(&self, cell: usize, direction: usize) -> bool {
+        let (word, mask) = self.slot(cell, direction);
+        self.generations[word] == self.generation && self.words[word] & mask != 0
+    }
+
+    pub fn clear(&mut self) {
+        if self.generation == u32::MAX {
+            self.generations.fill(0);
+            self.generation = 0;
+        }
+        self.generation += 1;
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn visited_set() {
+        let mut visited = VisitedSet::new(100, 4);
+        assert!(visited.insert(0, 0));
+        assert!(visited.insert(99, 3));
+        assert!(!visited.insert(99, 3));
+        assert!(visited.contains(0, 0));
+        assert!(!visited.contains(0, 1));
+
+        visited.clear();
+        assert!(!visited.contains(0, 0));
+        assert!(!visited.contains(99, 3));
+        assert!(visited.insert(99, 3));
+    }
+
+    #[test]
+    fn visited_set_generation_wrap() {
+        let mut visited = VisitedSet::new(10, 1);
+        visited.generation = u32::MAX;
+        visited.insert(3, 0);
+        visited.clear();
+        assert!(!visited.contains(3, 0));
+        assert!(visited.insert(3, 0));
+    }
+}
@@ -0,0 +1,253 @@
+use std::ops::{Add, Mul, Neg, Sub};
+
+/// A point (or vector) in `N` dimensions. Axis 0 is `x`, axis 1 is `y`, and so on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Point<const N: usize>(pub [i32; N]);
+
+impl<const N: usize> Default for Point<N> {
+    fn default() -> Self {
+        Point([0; N])
+    }
+}
+
+impl<const N: usize> Point<N> {
+    pub const ORIGIN: Point<N> = Point([0; N]);
+
+    pub fn manhattan(&self, other: &Point<N>) -> i32 {
+        (0..N)
+            .map(|axis| (self.0[axis] - other.0[axis]).abs())
+            .sum()
+    }
+
+    /// The `2 * N` neighbours sharing a face with this point.
+    pub fn orthogonal_neighbors(self) -> impl Iterator<Item = Point<N>> {
+        (0..N).flat_map(move |axis| {
+            [-1, 1].map(|delta| {
+                let mut neighbor = self;
+                neighbor.0[axis] += delta;
+                neighbor
+            })
+        })
+    }
+
+    /// The `3^N - 1` neighbours touching this point, diagonals included.
+    pub fn moore_neighbors(self) -> impl Iterator<Item = Point<N>> {
+        let count = 3usize.pow(N as u32);
+        (0..count)
+            .filter(move |i| *i != count / 2)
+            .map(move |mut i| {
+                let mut neighbor = self;
+                for coord in &mut neighbor.0 {
+                    *coord += (i % 3) as i32 - 1;
+                    i /= 3;
+                }
+                neighbor
+            })
+    }
+}
+
+impl<const N: usize> Add for Point<N> {
+    type Output = Point<N>;
+
+    fn add(mut self, rhs: Point<N>) -> Point<N> {
+        for (a, b) in self.0.iter_mut().zip(rhs.0) {
+            *a += b;
+        }
+        self
+    }
+}
+
+impl<const N: usize> Sub for Point<N> {
+    type Output = Point<N>;
+
+    fn sub(mut self, rhs: Point<N>) -> Point<N> {
+        for (a, b) in self.0.iter_mut().zip(rhs.0) {
+            *a -= b;
+        }
+        self
+    }
+}
+
+impl<const N: usize> Mul<i32> for Point<N> {
+    type Output = Point<N>;
+
+    fn mul(self, rhs: i32) -> Point<N> {
+        Point(self.0.map(|a| a * rhs))
+    }
+}
+
+impl<const N: usize> Neg for Point<N> {
+    type Output = Point<N>;
+
+    fn neg(self) -> Point<N> {
+        Point(self.0.map(|a| -a))
+    }
+}
+
+impl From<(i32, i32)> for Point<2> {
+    fn from((x, y): (i32, i32)) -> Self {
+        Point([x, y])
+    }
+}
+
+impl From<Point<2>> for (i32, i32) {
+    fn from(Point([x, y]): Point<2>) -> Self {
+        (x, y)
+    }
+}
+
+impl From<(i32, i32, i32)> for Point<3> {
+    fn from((x, y, z): (i32, i32, i32)) -> Self {
+        Point([x, y, z])
+    }
+}
+
+impl<const N: usize> From<[i32; N]> for Point<N> {
+    fn from(coords: [i32; N]) -> Self {
+        Point(coords)
+    }
+}
+
+/// Inclusive bounding box: a point is inside if `min <= point <= max` on every axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoundingBox<const N: usize> {
+    pub min: Point<N>,
+    pub max: Point<N>,
+}
+
+impl<const N: usize> BoundingBox<N> {
+    pub fn new(point: impl Into<Point<N>>) -> Self {
+        let point = point.into();
+        BoundingBox {
+            min: point,
+            max: point,
+        }
+    }
+
+    pub fn include(&mut self, point: impl Into<Point<N>>) {
+        let point = point.into();
+        for axis in 0..N {
+            self.min.0[axis] = self.min.0[axis].min(point.0[axis]);
+            self.max.0[axis] = self.max.0[axis].max(point.0[axis]);
+        }
+    }
+
+    pub fn contains(&self, point: impl Into<Point<N>>) -> bool {
+        let point = point.into();
+        (0..N).all(|axis| (self.min.0[axis]..=self.max.0[axis]).contains(&point.0[axis]))
+    }
+
+    /// Number of cells along `axis`.
+    pub fn size(&self, axis: usize) -> usize {
+        (self.max.0[axis] - self.min.0[axis] + 1) as usize
+    }
+
+    /// Number of cells in the box.
+    pub fn volume(&self) -> usize {
+        (0..N).map(|axis| self.size(axis)).product()
+    }
+
+    /// The box grown by `margin` cells on every side.
+    pub fn expanded(&self, margin: i32) -> Self {
+        BoundingBox {
+            min: Point(self.min.0.map(|c| c - margin)),
+            max: Point(self.max.0.map(|c| c + margin)),
+        }
+    }
+
+    /// Index of `point` with axis 0 varying fastest, `None` when outside the box.
+    pub fn index(&self, point: impl Into<Point<N>>) -> Option<usize> {
+        let point = point.into();
+        if !self.contains(point) {
+            return None;
+        }
+        let mut index = 0;
+        let mut stride = 1;
+        for axis in 0..N {
+            index += (point.0[axis] - self.min.0[axis]) as usize * stride;
+            stride *= self.size(axis);
+        }
+        Some(index)
+    }
+
+    /// Every point of the box, in [`BoundingBox::index`] order.
+    pub fn points(&self) -> impl Iterator<Item = Point<N>> + '_ {
+        (0..self.volume()).map(|mut index| {
+            let mut point = self.min;
+            for axis in 0..N {
+                point.0[axis] += (index % self.size(axis)) as i32;
+                index /= self.size(axis);
+            }
+            point
+        })
+    }
+}
+
+impl BoundingBox<2> {
+    pub fn width(&self) -> usize {
+        self.size(0)
+    }
+
+    pub fn height(&self) -> usize {
+        self.size(1)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn point_neighbors() {
+        let p = Point([0, 0, 0]);
+        assert_eq!(p.orthogonal_neighbors().count(), 6);
+        assert_eq!(p.moore_neighbors().count(), 26);
+        assert!(p.moore_neighbors().all(|n| n != p));
+        assert_eq!(Point([5, 5, 5, 5]).moore_neighbors().count(), 80);
+
+        let mut flat: Vec<(i32, i32)> = Point([1, 1]).moore_neighbors().map(Into::into).collect();
+        flat.sort();
+        assert_eq!(
+            flat,
+            vec![
+                (0, 0),
+                (0, 1),
+                (0, 2),
+                (1, 0),
+                (1, 2),
+                (2, 0),
+                (2, 1),
+                (2, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn point_ops() {
+        let a = Point([1, 2, 3]);
+        let b = Point([3, 2, 1]);
+        assert_eq!(a + b, Point([4, 4, 4]));
+        assert_eq!(a - b, Point([-2, 0, 2]));
+        assert_eq!((a - b) * 2, Point([-4, 0, 4]));
+        assert_eq!(-a, Point([-1, -2, -3]));
+        assert_eq!(a.manhattan(&b), 4);
+    }
+
+    #[test]
+    fn bounding_box() {
+        let mut bbox = BoundingBox::new([0, 0, 0]);
+        bbox.include([2, -1, 1]);
+        assert_eq!(bbox.volume(), 3 * 2 * 2);
+        assert!(bbox.contains([2, 0, 1]));
+        assert!(!bbox.contains([3, 0, 1]));
+        assert_eq!(bbox.index([0, -1, 0]), Some(0));
+        assert_eq!(bbox.index([1, -1, 0]), Some(1));
+        assert_eq!(bbox.index([0, 0, 0]), Some(3));
+        assert_eq!(bbox.index([0, -1, 1]), Some(6));
+        assert!(bbox
+            .points()
+            .enumerate()
+            .all(|(idx, p)| bbox.index(p) == Some(idx)));
+        assert_eq!(bbox.expanded(1).volume(), 5 * 4 * 4);
+    }
+}
@@ -0,0 +1,208 @@
+use fxhash::FxHashMap;
+
+use crate::bitset::VisitedSet;
+use crate::geometry::{BoundingBox, Point};
+
+/// Unbounded grid only storing the cells that were inserted.
+///
+/// The bounds grow with every inserted (or [`SparseGrid::include`]d) position, and are not
+/// shrunk by removals. Grids are 2D unless told otherwise, and accept `(x, y)` tuples.
+#[derive(Debug, Clone)]
+pub struct SparseGrid<T, const N: usize = 2> {
+    cells: FxHashMap<Point<N>, T>,
+    bounds: Option<BoundingBox<N>>,
+}
+
+impl<T, const N: usize> Default for SparseGrid<T, N> {
+    fn default() -> Self {
+        SparseGrid {
+            cells: FxHashMap::default(),
+            bounds: None,
+        }
+    }
+}
+
+impl<T> SparseGrid<T> {
+    /// Builds a grid from a puzzle input. Every character is in bounds, but only the ones
+    /// `f` maps to a value are stored.
+    pub fn parse(input: &str, mut f: impl FnMut((i32, i32), char) -> Option<T>) -> Self {
+        let mut grid = SparseGrid::new();
+        for (y, line) in input.lines().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                let pos = (x as i32, y as i32);
+                grid.include(pos);
+                if let Some(value) = f(pos, c) {
+                    grid.insert(pos, value);
+                }
+            }
+        }
+        grid
+    }
+}
+
+impl<T, const N: usize> SparseGrid<T, N> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Grows the bounds to contain `pos`, without storing anything there.
+    pub fn include(&mut self, pos: impl Into<Point<N>>) {
+        let pos = pos.into();
+        match &mut self.bounds {
+            Some(bounds) => bounds.include(pos),
+            None => self.bounds = Some(BoundingBox::new(pos)),
+        }
+    }
+
+    pub fn insert(&mut self, pos: impl Into<Point<N>>, value: T) -> Option<T> {
+        let pos = pos.into();
+        self.include(pos);
+        self.cells.insert(pos, value)
+    }
+
+    pub fn remove(&mut self, pos: impl Into<Point<N>>) -> Option<T> {
+        self.cells.remove(&pos.into())
+    }
+
+    pub fn get(&self, pos: impl Into<Point<N>>) -> Option<&T> {
+        self.cells.get(&pos.into())
+    }
+
+    /// Whether a value is stored at `pos`.
+    pub fn contains(&self, pos: impl Into<Point<N>>) -> bool {
+        self.cells.contains_key(&pos.into())
+    }
+
+    /// Whether `pos` lies within the bounds, limits included.
+    pub fn contains_in_bounds(&self, pos: impl Into<Point<N>>) -> bool {
+        let pos = pos.into();
+        self.bounds.is_some_and(|bounds| bounds.contains(pos))
+    }
+
+    pub fn bounds(&self) -> Option<BoundingBox<N>> {
+        self.bounds
+    }
+
+    /// Index of `pos` within the bounds, `None` when out of bounds.
+    pub fn index(&self, pos: impl Into<Point<N>>) -> Option<usize> {
+        self.bounds?.index(pos)
+    }
+
+    /// A [`VisitedSet`] covering every cell within the current bounds, see [`SparseGrid::index`].
+    pub fn visited_set(&self, directions: usize) -> VisitedSet {
+        let cells = self.bounds.map(|b| b.volume()).unwrap_or(0);
+        VisitedSet::new(cells, directions)
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Point<N>, &T)> + '_ {
+        self.cells.iter().map(|(pos, value)| (*pos, value))
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = Point<N>> + '_ {
+        self.cells.keys().copied()
+    }
+}
+
+impl<T, P: Into<Point<N>>, const N: usize> FromIterator<(P, T)> for SparseGrid<T, N> {
+    fn from_iter<I: IntoIterator<Item = (P, T)>>(iter: I) -> Self {
+        let mut grid = SparseGrid::new();
+        for (pos, value) in iter {
+            grid.insert(pos, value);
+        }
+        grid
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_bounds() {
+        let mut grid: SparseGrid<char> = SparseGrid::new();
+        assert!(!grid.contains_in_bounds((0, 0)));
+
+        grid.insert((2, 3), 'a');
+        grid.insert((-1, 5), 'b');
+        grid.include((4, 4));
+        assert_eq!(
+            grid.bounds(),
+            Some(BoundingBox {
+                min: Point([-1, 3]),
+                max: Point([4, 5])
+            })
+        );
+        assert_eq!(grid.len(), 2);
+
+        assert!(grid.contains_in_bounds((-1, 3)));
+        assert!(grid.contains_in_bounds((4, 5)));
+        assert!(!grid.contains_in_bounds((5, 5)));
+        assert!(!grid.contains_in_bounds((4, 6)));
+        assert!(!grid.contains_in_bounds((-2, 4)));
+
+        grid.remove((-1, 5));
+        assert!(grid.contains_in_bounds((-1, 5)));
+        assert!(!grid.contains((-1, 5)));
+    }
+
+    #[test]
+    fn grid_parse() {
+        let grid = SparseGrid::parse("#..\n..#\n...", |_, c| (c == '#').then_some(()));
+        assert_eq!(grid.len(), 2);
+        assert!(grid.contains((0, 0)));
+        assert!(grid.contains((2, 1)));
+        assert!(grid.contains_in_bounds((2, 2)));
+        assert!(!grid.contains_in_bounds((3, 2)));
+        let bounds = grid.bounds().expect("non empty grid");
+        assert_eq!((bounds.width(), bounds.height()), (3, 3));
+        assert_eq!(grid.index((0, 0)), Some(0));
+        assert_eq!(grid.index((2, 1)), Some(5));
+        assert_eq!(grid.index((3, 1)), None);
+    }
+
+    // Conway cubes: game of life in 3 and 4 dimensions
+    fn conway_cubes<const N: usize>(input: &str, cycles: usize) -> usize {
+        let mut active: SparseGrid<(), N> = SparseGrid::new();
+        for (y, line) in input.lines().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                if c == '#' {
+                    let mut pos = Point::ORIGIN;
+                    pos.0[0] = x as i32;
+                    pos.0[1] = y as i32;
+                    active.insert(pos, ());
+                }
+            }
+        }
+
+        for _ in 0..cycles {
+            let bounds = active.bounds().expect("active cells").expanded(1);
+            active = bounds
+                .points()
+                .filter(|pos| {
+                    let neighbors = pos
+                        .moore_neighbors()
+                        .filter(|n| active.contains(*n))
+                        .count();
+                    neighbors == 3 || (neighbors == 2 && active.contains(*pos))
+                })
+                .map(|pos| (pos, ()))
+                .collect();
+        }
+        active.len()
+    }
+
+    #[test]
+    fn grid_n_dimensions() {
+        let input = ".#.\n..#\n###";
+        assert_eq!(conway_cubes::<3>(input, 6), 112);
+        assert_eq!(conway_cubes::<4>(input, 6), 848);
+    }
+}
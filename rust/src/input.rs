@@ -0,0 +1,43 @@
+use std::path::Path;
+
+use memmap2::Mmap;
+
+/// Puzzle input mapped in memory, so big inputs are parsed in place instead of being copied.
+pub struct MappedInput {
+    map: Mmap,
+}
+
+impl MappedInput {
+    pub fn open(path: impl AsRef<Path>) -> Self {
+        let file = std::fs::File::open(path).expect("Failed to open file");
+        // SAFETY: inputs are read-only files that are not expected to change during a run.
+        let map = unsafe { Mmap::map(&file) }.expect("Failed to map file");
+        MappedInput { map }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.map
+    }
+
+    pub fn as_str(&self) -> &str {
+        std::str::from_utf8(&self.map).expect("Input is not valid UTF-8")
+    }
+}
+
+/// What a part takes as input: text, or the raw bytes for parts that parse them directly and
+/// don't need the whole file to be checked as UTF-8 first.
+pub trait FromInput<'a> {
+    fn from_input(input: &'a MappedInput) -> Self;
+}
+
+impl<'a> FromInput<'a> for &'a str {
+    fn from_input(input: &'a MappedInput) -> Self {
+        input.as_str()
+    }
+}
+
+impl<'a> FromInput<'a> for &'a [u8] {
+    fn from_input(input: &'a MappedInput) -> Self {
+        input.as_bytes()
+    }
+}
@@ -1,18 +1,53 @@
+pub mod bitset;
+pub mod geometry;
+pub mod grid;
+pub mod input;
+pub mod memo;
+#[doc(hidden)]
+pub mod rng;
+pub mod viz;
+pub mod word_search;
+
+/// Generates `main`, running every part on `inputs/<bin>.txt` (or `--input <path>`).
+/// Parts and extras take either a `&str` or a `&[u8]`, see [`input::FromInput`].
+///
+/// Extra commands can be listed after a `;`: `aoc!(part_one, part_two; explain)` runs
+/// `explain` instead of the parts when called with `--explain`. Underscores in their names
+/// become dashes in the flag.
 #[macro_export]
 macro_rules! aoc {
-    ($($call:ident),*) => {
+    ($($call:ident),* $(; $($extra:ident),+)?) => {
         fn main() {
-            use std::io::prelude::*;
-            let bin_name = env!("CARGO_BIN_NAME");
-            let file_input_name = format!("inputs/{bin_name}.txt");
-            let mut file_input = std::fs::File::open(file_input_name).expect("Failed to open file");
-            let mut input = String::new();
-            file_input.read_to_string(&mut input).expect("Failed to read file");
+            let bin_name = env!("CARGO_CRATE_NAME");
+            let args: Vec<String> = std::env::args().collect();
+            $crate::viz::init_from_args(bin_name, &args);
+            let file_input_name = match args.iter().position(|a| a == "--input") {
+                Some(idx) => args.get(idx + 1).expect("Usage: --input <path>").clone(),
+                None => format!("inputs/{bin_name}.txt"),
+            };
+            let input = $crate::input::MappedInput::open(file_input_name);
+
+            #[allow(unused_mut)]
+            let mut ran_extra = false;
+            $($(
+                let flag = format!("--{}", stringify!($extra).replace('_', "-"));
+                if args.contains(&flag) {
+                    ran_extra = true;
+                    println!("{}", $extra($crate::input::FromInput::from_input(&input)));
+                }
+            )+)?
+            if ran_extra {
+                return;
+            }
+
             $(
+                $crate::viz::begin(stringify!($call));
+                let part_input = $crate::input::FromInput::from_input(&input);
                 let before = std::time::Instant::now();
-                let result = $call(&input);
+                let result = $call(part_input);
                 let after = std::time::Instant::now();
                 let delta = after - before;
+                $crate::viz::finish();
                 println!("{}:", stringify!($call));
                 println!("{result}");
                 println!("---");
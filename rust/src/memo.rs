@@ -0,0 +1,208 @@
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::hash::Hash;
+
+use fxhash::FxHashMap;
+
+/// Hit/miss counters of a [`Memo`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MemoStats {
+    pub hits: usize,
+    pub misses: usize,
+    pub evictions: usize,
+}
+
+impl MemoStats {
+    pub fn lookups(&self) -> usize {
+        self.hits + self.misses
+    }
+
+    pub fn hit_rate(&self) -> f64 {
+        match self.lookups() {
+            0 => 0.,
+            lookups => self.hits as f64 / lookups as f64,
+        }
+    }
+}
+
+impl Display for MemoStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} hits, {} misses, {} evictions ({:.1}% hit rate)",
+            self.hits,
+            self.misses,
+            self.evictions,
+            self.hit_rate() * 100.
+        )
+    }
+}
+
+/// Cache for recursive solvers.
+///
+/// Unbounded by default. A bounded memo evicts the oldest entries first once full.
+#[derive(Debug)]
+pub struct Memo<K, V> {
+    values: FxHashMap<K, V>,
+    // Insertion order, only tracked when the memo is bounded
+    order: VecDeque<K>,
+    capacity: Option<usize>,
+    stats: MemoStats,
+}
+
+impl<K, V> Default for Memo<K, V> {
+    fn default() -> Self {
+        Memo {
+            values: FxHashMap::default(),
+            order: VecDeque::new(),
+            capacity: None,
+            stats: MemoStats::default(),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Memo<K, V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn bounded(capacity: usize) -> Self {
+        assert!(
+            capacity > 0,
+            "A bounded memo needs room for at least one value"
+        );
+        Memo {
+            capacity: Some(capacity),
+            ..Self::default()
+        }
+    }
+
+    pub fn stats(&self) -> MemoStats {
+        self.stats
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn get(&mut self, key: &K) -> Option<V> {
+        let value = self.values.get(key).cloned();
+        match value {
+            Some(_) => self.stats.hits += 1,
+            None => self.stats.misses += 1,
+        }
+        value
+    }
+
+    pub fn insert(&mut self, key: K, value: V) {
+        if let Some(capacity) = self.capacity {
+            if !self.values.contains_key(&key) {
+                while self.values.len() >= capacity {
+                    let oldest = self.order.pop_front().expect("order tracks every key");
+                    self.values.remove(&oldest);
+                    self.stats.evictions += 1;
+                }
+                self.order.push_back(key.clone());
+            }
+        }
+        self.values.insert(key, value);
+    }
+
+    /// Returns the memoized value for `key`, computing it with `compute` on a miss.
+    /// `compute` gets the memo back so it can recurse into it.
+    pub fn get_or_compute(&mut self, key: K, compute: impl FnOnce(&mut Self) -> V) -> V {
+        if let Some(value) = self.get(&key) {
+            return value;
+        }
+        let value = compute(self);
+        self.insert(key, value.clone());
+        value
+    }
+
+    /// Memoized recursion without a named helper function:
+    /// `solver` calls [`Recurse::call`] for its sub-problems.
+    pub fn recurse(&mut self, key: K, solver: &dyn Fn(&mut Recurse<'_, K, V>, K) -> V) -> V {
+        Recurse { memo: self, solver }.call(key)
+    }
+}
+
+/// Handle given to the solver of [`Memo::recurse`].
+pub struct Recurse<'a, K, V> {
+    memo: &'a mut Memo<K, V>,
+    solver: &'a dyn Fn(&mut Recurse<'_, K, V>, K) -> V,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Recurse<'_, K, V> {
+    pub fn call(&mut self, key: K) -> V {
+        if let Some(value) = self.memo.get(&key) {
+            return value;
+        }
+        let solver = self.solver;
+        let value = solver(self, key.clone());
+        self.memo.insert(key, value.clone());
+        value
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn fibonacci(memo: &mut Memo<u64, u64>, n: u64) -> u64 {
+        if n < 2 {
+            return n;
+        }
+        memo.get_or_compute(n, |memo| fibonacci(memo, n - 1) + fibonacci(memo, n - 2))
+    }
+
+    #[test]
+    fn memo_get_or_compute() {
+        let mut memo = Memo::new();
+        assert_eq!(fibonacci(&mut memo, 90), 2880067194370816120);
+        assert_eq!(memo.len(), 89);
+        assert_eq!(
+            memo.stats(),
+            MemoStats {
+                hits: 87,
+                misses: 89,
+                evictions: 0
+            }
+        );
+    }
+
+    #[test]
+    fn memo_recurse() {
+        let mut memo = Memo::new();
+        let fibonacci = memo.recurse(90, &|rec, n: u64| match n {
+            0 | 1 => n,
+            n => rec.call(n - 1) + rec.call(n - 2),
+        });
+        assert_eq!(fibonacci, 2880067194370816120);
+    }
+
+    #[test]
+    fn memo_bounded() {
+        let mut memo = Memo::bounded(2);
+        memo.insert(1, 'a');
+        memo.insert(2, 'b');
+        memo.insert(2, 'c');
+        assert_eq!(memo.stats().evictions, 0);
+        memo.insert(3, 'd');
+        assert_eq!(memo.len(), 2);
+        assert_eq!(memo.get(&1), None);
+        assert_eq!(memo.get(&2), Some('c'));
+        assert_eq!(memo.get(&3), Some('d'));
+        assert_eq!(
+            memo.stats(),
+            MemoStats {
+                hits: 2,
+                misses: 1,
+                evictions: 1
+            }
+        );
+    }
+}
@@ -0,0 +1,43 @@
+//! Deterministic pseudo-random numbers for the randomized tests of the days.
+//!
+//! Public so that the tests of every binary can use it, it isn't meant for anything else.
+
+/// Xorshift64 generator: fast, seedable, and good enough to generate puzzle-like inputs.
+#[derive(Debug, Clone)]
+pub struct XorShift {
+    state: u64,
+}
+
+impl XorShift {
+    pub fn new(seed: u64) -> Self {
+        assert!(seed != 0, "A xorshift seed can't be zero");
+        XorShift { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// A number in `0..max`. Slightly biased towards small numbers, which tests don't mind.
+    pub fn below(&mut self, max: u64) -> u64 {
+        self.next_u64() % max
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn xorshift() {
+        let mut rng = XorShift::new(1);
+        assert_eq!(rng.next_u64(), 1082269761);
+        let mut again = XorShift::new(1);
+        again.next_u64();
+        assert!((0..1000).all(|_| rng.below(7) == again.below(7)));
+        assert!((0..1000).all(|_| rng.below(7) < 7));
+    }
+}
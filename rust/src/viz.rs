@@ -0,0 +1,567 @@
+//! Rendering of grids and simulations, either as ANSI text or as image frames on disk.
+//!
+//! Days build a [`Frame`] and hand it to [`emit`], which is a no-op unless the runner was
+//! started with `--viz <ansi|ppm|png|gif>` (and optionally `--viz-out <dir>`).
+
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Color(pub u8, pub u8, pub u8);
+
+impl Color {
+    pub const BLACK: Color = Color(16, 16, 16);
+    pub const GRAY: Color = Color(128, 128, 128);
+    pub const WHITE: Color = Color(240, 240, 240);
+    pub const RED: Color = Color(220, 50, 47);
+    pub const GREEN: Color = Color(80, 200, 60);
+    pub const BLUE: Color = Color(38, 139, 210);
+    pub const YELLOW: Color = Color(230, 200, 40);
+    pub const CYAN: Color = Color(42, 190, 190);
+    pub const MAGENTA: Color = Color(211, 54, 130);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cell {
+    pub glyph: char,
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+}
+
+impl Cell {
+    // Colour of the cell once rasterised, one cell being a block of pixels
+    fn pixel(&self) -> Color {
+        match (self.bg, self.fg, self.glyph) {
+            (Some(bg), _, _) => bg,
+            (None, Some(fg), _) => fg,
+            (None, None, '.' | ' ') => Color::BLACK,
+            (None, None, '#') => Color::GRAY,
+            (None, None, _) => Color::WHITE,
+        }
+    }
+}
+
+/// A grid of glyphs with colour overlays. Coordinates are `(x, y)`, origin in the top left.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+}
+
+impl Frame {
+    pub fn new(width: usize, height: usize) -> Self {
+        let empty = Cell {
+            glyph: '.',
+            fg: None,
+            bg: None,
+        };
+        Frame {
+            width,
+            height,
+            cells: vec![empty; width * height],
+        }
+    }
+
+    pub fn parse(input: &str) -> Self {
+        let width = input.lines().map(|l| l.chars().count()).max().unwrap_or(0);
+        let height = input.lines().count();
+        let mut frame = Frame::new(width, height);
+        for (y, line) in input.lines().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                frame.set((x as i32, y as i32), c);
+            }
+        }
+        frame
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn index(&self, (x, y): (i32, i32)) -> Option<usize> {
+        let in_bounds = x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height;
+        in_bounds.then(|| y as usize * self.width + x as usize)
+    }
+
+    /// Out of bounds positions are ignored, so callers can draw without clipping.
+    pub fn cell_mut(&mut self, pos: (i32, i32)) -> Option<&mut Cell> {
+        let idx = self.index(pos)?;
+        Some(&mut self.cells[idx])
+    }
+
+    pub fn get(&self, pos: (i32, i32)) -> Option<&Cell> {
+        self.index(pos).map(|idx| &self.cells[idx])
+    }
+
+    pub fn set(&mut self, pos: (i32, i32), glyph: char) {
+        if let Some(cell) = self.cell_mut(pos) {
+            cell.glyph = glyph;
+        }
+    }
+
+    /// Colours the background of `cells`.
+    pub fn highlight(&mut self, cells: impl IntoIterator<Item = (i32, i32)>, color: Color) {
+        for pos in cells {
+            if let Some(cell) = self.cell_mut(pos) {
+                cell.bg = Some(color);
+            }
+        }
+    }
+
+    /// Draws a path: empty cells get a `*`, every cell of the path is coloured.
+    pub fn path(&mut self, cells: impl IntoIterator<Item = (i32, i32)>, color: Color) {
+        for pos in cells {
+            if let Some(cell) = self.cell_mut(pos) {
+                if cell.glyph == '.' {
+                    cell.glyph = '*';
+                }
+                cell.fg = Some(color);
+            }
+        }
+    }
+
+    pub fn entity(&mut self, pos: (i32, i32), glyph: char, color: Color) {
+        if let Some(cell) = self.cell_mut(pos) {
+            cell.glyph = glyph;
+            cell.fg = Some(color);
+        }
+    }
+
+    /// Plain text, overlays dropped.
+    pub fn to_text(&self) -> String {
+        let mut text = String::with_capacity((self.width + 1) * self.height);
+        for row in self.cells.chunks(self.width.max(1)) {
+            text.extend(row.iter().map(|c| c.glyph));
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Text with 24-bit ANSI colour escapes for the overlays.
+    pub fn to_ansi(&self) -> String {
+        let mut text = String::new();
+        for row in self.cells.chunks(self.width.max(1)) {
+            for cell in row {
+                if let Some(Color(r, g, b)) = cell.fg {
+                    write!(text, "\x1b[38;2;{r};{g};{b}m").expect("String write");
+                }
+                if let Some(Color(r, g, b)) = cell.bg {
+                    write!(text, "\x1b[48;2;{r};{g};{b}m").expect("String write");
+                }
+                text.push(cell.glyph);
+                if cell.fg.is_some() || cell.bg.is_some() {
+                    text.push_str("\x1b[0m");
+                }
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Rasterises the frame, every cell becoming a `scale`x`scale` block.
+    pub fn to_image(&self, scale: usize) -> Image {
+        let width = self.width * scale;
+        let height = self.height * scale;
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                pixels.push(self.cells[(y / scale) * self.width + x / scale].pixel());
+            }
+        }
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Color>,
+}
+
+impl Image {
+    fn rgb(&self) -> impl Iterator<Item = [u8; 3]> + '_ {
+        self.pixels.iter().map(|Color(r, g, b)| [*r, *g, *b])
+    }
+
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let mut bytes = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        bytes.extend(self.rgb().flatten());
+        bytes
+    }
+
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut raw = Vec::with_capacity((self.width * 3 + 1) * self.height);
+        for row in self.pixels.chunks(self.width.max(1)) {
+            // Filter type: none
+            raw.push(0);
+            raw.extend(row.iter().flat_map(|Color(r, g, b)| [*r, *g, *b]));
+        }
+
+        let mut ihdr = Vec::with_capacity(13);
+        ihdr.extend((self.width as u32).to_be_bytes());
+        ihdr.extend((self.height as u32).to_be_bytes());
+        // 8 bits per channel, RGB, default compression/filter, no interlace
+        ihdr.extend([8, 2, 0, 0, 0]);
+
+        let mut bytes = b"\x89PNG\r\n\x1a\n".to_vec();
+        png_chunk(&mut bytes, b"IHDR", &ihdr);
+        png_chunk(&mut bytes, b"IDAT", &zlib_stored(&raw));
+        png_chunk(&mut bytes, b"IEND", &[]);
+        bytes
+    }
+}
+
+fn png_chunk(bytes: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    bytes.extend((data.len() as u32).to_be_bytes());
+    let start = bytes.len();
+    bytes.extend(kind);
+    bytes.extend(data);
+    let crc = crc32(&bytes[start..]);
+    bytes.extend(crc.to_be_bytes());
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+// zlib stream made of uncompressed deflate blocks. Frames are small, size isn't a concern.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![0x78, 0x01];
+    let mut blocks = data.chunks(u16::MAX as usize).peekable();
+    if blocks.peek().is_none() {
+        bytes.extend([1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        bytes.push(last as u8);
+        bytes.extend(len.to_le_bytes());
+        bytes.extend((!len).to_le_bytes());
+        bytes.extend(block);
+    }
+
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    bytes.extend(((b << 16) | a).to_be_bytes());
+    bytes
+}
+
+/// Encodes `images` as a looping animated GIF, `delay` being in hundredths of a second.
+/// All images must share the dimensions of the first one.
+pub fn gif(images: &[Image], delay: u16) -> Vec<u8> {
+    let Some(first) = images.first() else {
+        return Vec::new();
+    };
+
+    let mut palette: Vec<Color> = Vec::new();
+    for image in images {
+        for pixel in &image.pixels {
+            if !palette.contains(pixel) && palette.len() <= 256 {
+                palette.push(*pixel);
+            }
+        }
+    }
+    // Fall back on a 6x6x6 colour cube if the frames use too many colours
+    let quantize = palette.len() > 256;
+    if quantize {
+        palette = (0..216)
+            .map(|i| {
+                Color(
+                    (i / 36) as u8 * 51,
+                    (i / 6 % 6) as u8 * 51,
+                    (i % 6) as u8 * 51,
+                )
+            })
+            .collect();
+    }
+    let palette_index = |pixel: &Color| -> u8 {
+        if quantize {
+            let cube = |c: u8| (c as usize + 25) / 51;
+            (cube(pixel.0) * 36 + cube(pixel.1) * 6 + cube(pixel.2)) as u8
+        } else {
+            palette
+                .iter()
+                .position(|c| c == pixel)
+                .expect("color in palette") as u8
+        }
+    };
+
+    let mut bytes = b"GIF89a".to_vec();
+    bytes.extend((first.width as u16).to_le_bytes());
+    bytes.extend((first.height as u16).to_le_bytes());
+    // Global colour table of 256 entries
+    bytes.extend([0xf7, 0, 0]);
+    for i in 0..256 {
+        let Color(r, g, b) = palette.get(i).copied().unwrap_or(Color(0, 0, 0));
+        bytes.extend([r, g, b]);
+    }
+    // Loop forever
+    bytes.extend(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00");
+
+    for image in images {
+        assert_eq!(
+            (image.width, image.height),
+            (first.width, first.height),
+            "GIF frames must have the same size"
+        );
+        bytes.extend([0x21, 0xf9, 0x04, 0x00]);
+        bytes.extend(delay.to_le_bytes());
+        bytes.extend([0, 0]);
+
+        bytes.push(0x2c);
+        bytes.extend([0, 0, 0, 0]);
+        bytes.extend((image.width as u16).to_le_bytes());
+        bytes.extend((image.height as u16).to_le_bytes());
+        bytes.push(0);
+
+        let indices: Vec<u8> = image.pixels.iter().map(palette_index).collect();
+        bytes.push(8);
+        for block in lzw(&indices, 8).chunks(255) {
+            bytes.push(block.len() as u8);
+            bytes.extend(block);
+        }
+        bytes.push(0);
+    }
+    bytes.push(0x3b);
+    bytes
+}
+
+// GIF flavoured LZW: variable code width, LSB first, dictionary reset when full
+fn lzw(data: &[u8], min_code_size: u32) -> Vec<u8> {
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+
+    let mut out = Vec::new();
+    let (mut buffer, mut bits) = (0u32, 0u32);
+    let mut code_size = min_code_size + 1;
+    let mut emit = |code: u16, code_size: u32, out: &mut Vec<u8>| {
+        buffer |= (code as u32) << bits;
+        bits += code_size;
+        while bits >= 8 {
+            out.push(buffer as u8);
+            buffer >>= 8;
+            bits -= 8;
+        }
+    };
+
+    let mut dictionary: fxhash::FxHashMap<(u16, u8), u16> = fxhash::FxHashMap::default();
+    let mut next_code = end + 1;
+    emit(clear, code_size, &mut out);
+
+    let mut data = data.iter();
+    let Some(&first) = data.next() else {
+        emit(end, code_size, &mut out);
+        emit(0, 7, &mut out);
+        return out;
+    };
+    let mut prefix = first as u16;
+    for &byte in data {
+        if let Some(&code) = dictionary.get(&(prefix, byte)) {
+            prefix = code;
+            continue;
+        }
+        emit(prefix, code_size, &mut out);
+        if next_code == 4096 {
+            emit(clear, code_size, &mut out);
+            dictionary.clear();
+            next_code = end + 1;
+            code_size = min_code_size + 1;
+        } else {
+            dictionary.insert((prefix, byte), next_code);
+            if next_code == 1 << code_size {
+                code_size += 1;
+            }
+            next_code += 1;
+        }
+        prefix = byte as u16;
+    }
+    emit(prefix, code_size, &mut out);
+    emit(end, code_size, &mut out);
+    // Flush the remaining bits
+    emit(0, 7, &mut out);
+    out
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Ansi,
+    Ppm,
+    Png,
+    Gif,
+}
+
+impl Format {
+    pub fn parse(format: &str) -> Option<Format> {
+        match format {
+            "ansi" => Some(Format::Ansi),
+            "ppm" => Some(Format::Ppm),
+            "png" => Some(Format::Png),
+            "gif" => Some(Format::Gif),
+            _ => None,
+        }
+    }
+}
+
+// Pixels per cell in image outputs
+const SCALE: usize = 4;
+// Delay between GIF frames, in hundredths of a second
+const GIF_DELAY: u16 = 5;
+
+struct Recorder {
+    format: Format,
+    dir: PathBuf,
+    part: String,
+    count: usize,
+    gif_frames: Vec<Image>,
+}
+
+impl Recorder {
+    fn record(&mut self, frame: Frame) {
+        let path = self.dir.join(format!("{}_{:05}", self.part, self.count));
+        self.count += 1;
+        match self.format {
+            Format::Ansi => {
+                let mut stdout = std::io::stdout().lock();
+                // Clear the screen and go back to the top left corner
+                write!(stdout, "\x1b[2J\x1b[H{}", frame.to_ansi()).expect("Failed to write frame");
+            }
+            Format::Ppm => write_file(&path.with_extension("ppm"), &frame.to_image(SCALE).to_ppm()),
+            Format::Png => write_file(&path.with_extension("png"), &frame.to_image(SCALE).to_png()),
+            Format::Gif => self.gif_frames.push(frame.to_image(SCALE)),
+        }
+    }
+
+    fn finish(&mut self) {
+        if self.format == Format::Gif && !self.gif_frames.is_empty() {
+            let path = self.dir.join(&self.part).with_extension("gif");
+            write_file(&path, &gif(&self.gif_frames, GIF_DELAY));
+            self.gif_frames.clear();
+        }
+        self.count = 0;
+    }
+}
+
+fn write_file(path: &Path, bytes: &[u8]) {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).expect("Failed to create viz directory");
+    }
+    std::fs::write(path, bytes).expect("Failed to write viz frame");
+}
+
+static RECORDER: Mutex<Option<Recorder>> = Mutex::new(None);
+
+/// Reads `--viz <format>` and `--viz-out <dir>` from the runner arguments.
+/// Frames are written to `viz/<bin_name>` unless told otherwise.
+pub fn init_from_args(bin_name: &str, args: &[String]) {
+    let flag = |name: &str| {
+        args.iter()
+            .position(|a| a == name)
+            .map(|idx| args.get(idx + 1).expect("Missing flag value").as_str())
+    };
+    let Some(format) = flag("--viz") else { return };
+    let format = Format::parse(format).expect("--viz expects one of ansi, ppm, png, gif");
+    let dir = flag("--viz-out")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new("viz").join(bin_name));
+    *RECORDER.lock().expect("viz lock") = Some(Recorder {
+        format,
+        dir,
+        part: String::new(),
+        count: 0,
+        gif_frames: Vec::new(),
+    });
+}
+
+pub fn enabled() -> bool {
+    RECORDER.lock().expect("viz lock").is_some()
+}
+
+/// Records a frame. The frame is only built when visualisation is enabled.
+pub fn emit(frame: impl FnOnce() -> Frame) {
+    if let Some(recorder) = RECORDER.lock().expect("viz lock").as_mut() {
+        recorder.record(frame());
+    }
+}
+
+/// Called by the runner before each part, so frames are grouped by part.
+pub fn begin(part: &str) {
+    if let Some(recorder) = RECORDER.lock().expect("viz lock").as_mut() {
+        recorder.part = part.to_string();
+        recorder.count = 0;
+    }
+}
+
+/// Called by the runner after each part to flush buffered outputs (GIF).
+pub fn finish() {
+    if let Some(recorder) = RECORDER.lock().expect("viz lock").as_mut() {
+        recorder.finish();
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn viz_overlays() {
+        let mut frame = Frame::parse("#..\n...\n..#");
+        frame.path([(0, 1), (1, 1), (2, 1)], Color::GREEN);
+        frame.entity((1, 0), '^', Color::RED);
+        frame.highlight([(2, 2), (5, 5)], Color::BLUE);
+        assert_eq!(frame.to_text(), "#^.\n***\n..#\n");
+        assert_eq!(
+            frame.to_ansi().lines().next(),
+            Some("#\x1b[38;2;220;50;47m^\x1b[0m.")
+        );
+        assert_eq!(frame.get((2, 2)).map(Cell::pixel), Some(Color::BLUE));
+    }
+
+    #[test]
+    fn viz_images() {
+        let image = Frame::parse("#.\n.#").to_image(2);
+        assert_eq!((image.width, image.height), (4, 4));
+
+        let ppm = image.to_ppm();
+        assert!(ppm.starts_with(b"P6\n4 4\n255\n"));
+        assert_eq!(ppm.len(), 11 + 4 * 4 * 3);
+
+        let png = image.to_png();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR"));
+        assert!(png.ends_with(&[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]));
+
+        let gif = gif(&[image.clone(), image], 10);
+        assert!(gif.starts_with(b"GIF89a\x04\x00\x04\x00"));
+        assert_eq!(gif.last(), Some(&0x3b));
+    }
+
+    #[test]
+    fn viz_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+    }
+}
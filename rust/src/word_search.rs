@@ -0,0 +1,372 @@
+use fxhash::FxHashSet;
+
+use crate::grid::SparseGrid;
+use crate::viz::{Color, Frame};
+
+/// The 8 directions a word can be read in, `(dx, dy)`.
+pub const DIRECTIONS: [(i32, i32); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    /// Index of the word in the searched list
+    pub word: usize,
+    pub start: (i32, i32),
+    pub direction: (i32, i32),
+    /// Cells covered, from the first letter to the last
+    pub cells: Vec<(i32, i32)>,
+}
+
+/// Word search over a character grid.
+///
+/// A palindrome reads the same both ways, so it is only reported once, in the direction
+/// starting from its smallest end.
+pub struct WordSearch<'g> {
+    grid: &'g SparseGrid<char>,
+    wrap: bool,
+}
+
+impl<'g> WordSearch<'g> {
+    pub fn new(grid: &'g SparseGrid<char>) -> Self {
+        WordSearch { grid, wrap: false }
+    }
+
+    /// Lets words continue on the opposite edge of the grid. A word can't use a cell twice.
+    pub fn wrapping(mut self, wrap: bool) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    fn cell(&self, (x, y): (i32, i32)) -> Option<(i32, i32)> {
+        let bounds = self.grid.bounds()?;
+        let pos = if self.wrap {
+            let (min_x, min_y) = bounds.min.into();
+            (
+                min_x + (x - min_x).rem_euclid(bounds.width() as i32),
+                min_y + (y - min_y).rem_euclid(bounds.height() as i32),
+            )
+        } else {
+            (x, y)
+        };
+        self.grid.contains_in_bounds(pos).then_some(pos)
+    }
+
+    fn read(&self, word: &str, start: (i32, i32), (dx, dy): (i32, i32)) -> Option<Vec<(i32, i32)>> {
+        let mut cells = Vec::with_capacity(word.len());
+        for (d, letter) in word.chars().enumerate() {
+            let d = d as i32;
+            let pos = self.cell((start.0 + dx * d, start.1 + dy * d))?;
+            if self.grid.get(pos) != Some(&letter) || cells.contains(&pos) {
+                return None;
+            }
+            cells.push(pos);
+        }
+        Some(cells)
+    }
+
+    pub fn find(&self, words: &[&str]) -> Vec<Match> {
+        let mut matches = Vec::new();
+        for (idx, word) in words.iter().enumerate() {
+            let Some(first) = word.chars().next() else {
+                continue;
+            };
+            let palindrome = word.chars().eq(word.chars().rev());
+            let mut seen: FxHashSet<Vec<(i32, i32)>> = FxHashSet::default();
+
+            let mut starts: Vec<(i32, i32)> = self
+                .grid
+                .iter()
+                .filter(|(_, c)| **c == first)
+                .map(|(pos, _)| pos.into())
+                .collect();
+            starts.sort();
+            for start in starts {
+                for direction in DIRECTIONS {
+                    let Some(cells) = self.read(word, start, direction) else {
+                        continue;
+                    };
+                    if palindrome {
+                        let mut key = cells.clone();
+                        if key.last() < key.first() {
+                            key.reverse();
+                        }
+                        if !seen.insert(key) {
+                            continue;
+                        }
+                    }
+                    matches.push(Match {
+                        word: idx,
+                        start,
+                        direction,
+                        cells,
+                    });
+                }
+            }
+        }
+        matches
+    }
+
+    pub fn count(&self, words: &[&str]) -> usize {
+        self.find(words).len()
+    }
+
+    /// The grid with every matched cell highlighted.
+    pub fn render(&self, matches: &[Match]) -> Frame {
+        let Some(bounds) = self.grid.bounds() else {
+            return Frame::new(0, 0);
+        };
+        let (min_x, min_y) = bounds.min.into();
+        let mut frame = Frame::new(bounds.width(), bounds.height());
+        for (pos, c) in self.grid.iter() {
+            let (x, y) = pos.into();
+            frame.set((x - min_x, y - min_y), *c);
+        }
+        for m in matches {
+            let cells = m.cells.iter().map(|(x, y)| (x - min_x, y - min_y));
+            frame.highlight(cells, Color::BLUE);
+        }
+        frame
+    }
+}
+
+/// A small 2D pattern to look for, `.` matching any character.
+///
+/// The pattern keeps its whole rectangle, wildcards included: a match needs every cell of it
+/// to be on the grid, and its origin is the top left corner of the rectangle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    /// Every cell relative to the top left corner and sorted, wildcards being `None`
+    cells: Vec<((i32, i32), Option<char>)>,
+}
+
+impl Template {
+    /// Short lines are padded with wildcards up to the longest one.
+    pub fn parse(pattern: &str) -> Self {
+        let width = pattern
+            .lines()
+            .map(|l| l.chars().count())
+            .max()
+            .unwrap_or(0);
+        let cells = pattern
+            .lines()
+            .enumerate()
+            .flat_map(|(y, line)| {
+                line.chars()
+                    .map(|c| (c != '.').then_some(c))
+                    .chain(std::iter::repeat(None))
+                    .take(width)
+                    .enumerate()
+                    .map(move |(x, c)| ((x as i32, y as i32), c))
+            })
+            .collect();
+        Template::normalized(cells)
+    }
+
+    fn normalized(mut cells: Vec<((i32, i32), Option<char>)>) -> Self {
+        let min_x = cells.iter().map(|((x, _), _)| *x).min().unwrap_or(0);
+        let min_y = cells.iter().map(|((_, y), _)| *y).min().unwrap_or(0);
+        for ((x, y), _) in &mut cells {
+            *x -= min_x;
+            *y -= min_y;
+        }
+        cells.sort();
+        Template { cells }
+    }
+
+    /// The distinct rotations and reflections of the template, itself included.
+    pub fn variants(&self) -> Vec<Template> {
+        let mut variants: Vec<Template> = Vec::new();
+        for reflect in [false, true] {
+            let mut cells: Vec<((i32, i32), Option<char>)> = self
+                .cells
+                .iter()
+                .map(|&((x, y), c)| (if reflect { (-x, y) } else { (x, y) }, c))
+                .collect();
+            for _ in 0..4 {
+                let variant = Template::normalized(cells.clone());
+                if !variants.contains(&variant) {
+                    variants.push(variant);
+                }
+                // Quarter turn
+                for ((x, y), _) in &mut cells {
+                    (*x, *y) = (-*y, *x);
+                }
+            }
+        }
+        variants
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateMatch {
+    /// Index in [`Template::variants`]
+    pub variant: usize,
+    /// Grid position of the variant's top left corner
+    pub origin: (i32, i32),
+    /// Cells matched by non wildcard characters
+    pub cells: Vec<(i32, i32)>,
+}
+
+impl WordSearch<'_> {
+    /// Every occurrence of any variant of `template`.
+    pub fn find_template(&self, template: &Template) -> Vec<TemplateMatch> {
+        let Some(bounds) = self.grid.bounds() else {
+            return Vec::new();
+        };
+        let mut matches = Vec::new();
+        for (variant, Template { cells }) in template.variants().iter().enumerate() {
+            for origin in bounds.points() {
+                let (ox, oy) = origin.into();
+                let matched: Option<Vec<Option<(i32, i32)>>> = cells
+                    .iter()
+                    .map(|((x, y), c)| {
+                        let pos = self.cell((ox + x, oy + y))?;
+                        match c {
+                            None => Some(None),
+                            Some(c) => (self.grid.get(pos) == Some(c)).then_some(Some(pos)),
+                        }
+                    })
+                    .collect();
+                if let Some(matched) = matched {
+                    let cells = matched.into_iter().flatten().collect();
+                    matches.push(TemplateMatch {
+                        variant,
+                        origin: (ox, oy),
+                        cells,
+                    });
+                }
+            }
+        }
+        matches
+    }
+
+    pub fn count_template(&self, template: &Template) -> usize {
+        self.find_template(template).len()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn grid(input: &str) -> SparseGrid<char> {
+        SparseGrid::parse(input, |_, c| Some(c))
+    }
+
+    #[test]
+    fn word_search_matches() {
+        let grid = grid("CAT.\n.A..\nT.TX");
+        let search = WordSearch::new(&grid);
+        let matches = search.find(&["CAT", "DOG"]);
+        assert_eq!(
+            matches,
+            vec![
+                Match {
+                    word: 0,
+                    start: (0, 0),
+                    direction: (1, 0),
+                    cells: vec![(0, 0), (1, 0), (2, 0)]
+                },
+                Match {
+                    word: 0,
+                    start: (0, 0),
+                    direction: (1, 1),
+                    cells: vec![(0, 0), (1, 1), (2, 2)]
+                },
+            ]
+        );
+        assert_eq!(search.render(&matches).to_text(), "CAT.\n.A..\nT.TX\n");
+    }
+
+    #[test]
+    fn word_search_palindromes() {
+        let grid = grid("ABA\nB.B\nABA");
+        let search = WordSearch::new(&grid);
+        // Every side reads ABA both ways
+        assert_eq!(search.count(&["ABA"]), 4);
+        assert_eq!(search.count(&["A"]), 4);
+        assert_eq!(search.count(&["AB"]), 8);
+    }
+
+    #[test]
+    fn word_search_wrapping() {
+        let grid = grid("TXCA\n....");
+        assert_eq!(WordSearch::new(&grid).count(&["CAT"]), 0);
+        let search = WordSearch::new(&grid).wrapping(true);
+        let matches = search.find(&["CAT"]);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].cells, vec![(2, 0), (3, 0), (0, 0)]);
+        // Reading CATXC would need to reuse the C
+        assert_eq!(search.count(&["CATXC"]), 0);
+    }
+
+    #[test]
+    fn template_variants() {
+        let x_mas = Template::parse("M.S\n.A.\nM.S");
+        assert_eq!(x_mas.variants().len(), 4);
+        let plus = Template::parse(".X.\nXXX\n.X.");
+        assert_eq!(plus.variants(), vec![plus.clone()]);
+        let l = Template::parse("A.\nBC");
+        assert_eq!(l.variants().len(), 8);
+    }
+
+    #[test]
+    fn template_wildcard_margins() {
+        // The leading wildcards are part of the template, so the A isn't alone
+        let corner = Template::parse("..\n.A");
+        assert_eq!(corner.variants().len(), 4);
+        let short = Template::parse(".AB\n.");
+        assert_eq!(short, Template::parse(".AB\n..."));
+
+        let search_grid = grid("XYZ\nQAB");
+        let search = WordSearch::new(&search_grid);
+        let matches = search.find_template(&Template::parse("...\n.AB"));
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].origin, (0, 0));
+        assert_eq!(matches[0].cells, vec![(1, 1), (2, 1)]);
+
+        // Not enough room for the wildcard column
+        let tight = grid("AB\nXY");
+        assert_eq!(
+            WordSearch::new(&tight).count_template(&Template::parse(".AB")),
+            0
+        );
+        let wrapping = WordSearch::new(&tight).wrapping(true);
+        let matches = wrapping.find_template(&Template::parse(".AB"));
+        // Read left to right, and right to left as the half turn BA.
+        let found: Vec<(usize, (i32, i32))> =
+            matches.iter().map(|m| (m.variant, m.origin)).collect();
+        assert_eq!(found, vec![(0, (1, 0)), (2, (1, 0))]);
+    }
+
+    #[test]
+    fn template_matches() {
+        let pluses = grid("XMXM\nMMMX\nXMXM\n.M..");
+        let search = WordSearch::new(&pluses);
+        let plus = Template::parse(".M.\nMMM\n.M.");
+        let matches = search.find_template(&plus);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].origin, (0, 0));
+        assert_eq!(
+            matches[0].cells,
+            vec![(0, 1), (1, 0), (1, 1), (1, 2), (2, 1)]
+        );
+
+        // Longer crosses, with wrap-around
+        let cross = Template::parse("X...X\n.M.M.\n..A..\n.M.M.\nX...X");
+        let wrapped = grid("A....\n.M..M\n..XX.\n..XX.\n.M..M");
+        assert_eq!(WordSearch::new(&wrapped).count_template(&cross), 0);
+        let wrapping = WordSearch::new(&wrapped).wrapping(true);
+        let matches = wrapping.find_template(&cross);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].origin, (3, 3));
+    }
+}